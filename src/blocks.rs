@@ -1,14 +1,46 @@
-type Offset = u64;
+pub type Offset = u64;
 
-use crate::utils::{bitrank, bitselectv, ffs, saturating_bitmask, wrapping_popcntv};
+use crate::utils::{bitrank, bitselectv, ffs, ffsv, saturating_bitmask, wrapping_popcntv};
 use crate::SLOTS_PER_BLOCK;
 
-// pub mod u64_soa_blocks;
+pub mod packed_blocks;
+pub mod slot_blocks;
 pub mod u32_blocks;
 pub mod u64_blocks;
+pub mod u64_soa_blocks;
+
+/// Integer types stored in the slots of a block.
+pub trait SlotValue: Copy + Default + Ord + std::fmt::Debug + Into<u64> {
+    /// Truncates `value` to the width of the slot.
+    fn from_u64(value: u64) -> Self;
+}
+
+impl SlotValue for u8 {
+    fn from_u64(value: u64) -> Self {
+        value as u8
+    }
+}
+
+impl SlotValue for u16 {
+    fn from_u64(value: u64) -> Self {
+        value as u16
+    }
+}
+
+impl SlotValue for u32 {
+    fn from_u64(value: u64) -> Self {
+        value as u32
+    }
+}
+
+impl SlotValue for u64 {
+    fn from_u64(value: u64) -> Self {
+        value
+    }
+}
 
 pub trait Blocks {
-    type Remainder;
+    type Remainder: SlotValue;
     #[inline(always)]
     fn split_quotient(quotient: u64) -> (usize, usize) {
         let block_index = (quotient / crate::SLOTS_PER_BLOCK as u64) as usize;
@@ -97,7 +129,20 @@ pub trait Blocks {
 
     // fn advise_normal(&self);
 
-    fn bytes_needed(num_blocks: usize) -> usize;
+    /// Returns the size of `num_blocks` blocks holding `remainder_bits` bit remainders.
+    fn bytes_needed(num_blocks: usize, remainder_bits: u64) -> usize;
+
+    /// Wraps the `num_blocks` blocks of `remainder_bits` bit remainders starting at `ptr`.
+    ///
+    /// # Safety
+    /// `ptr` must point to `bytes_needed(num_blocks, remainder_bits)` bytes, suitably aligned
+    /// and valid for as long as the blocks are used.
+    unsafe fn from_raw_parts(ptr: *mut u8, num_blocks: usize, remainder_bits: u64) -> Self;
+
+    /// Returns whether the blocks can hold remainders of `remainder_bits` bits.
+    fn supports_remainder_bits(remainder_bits: u64) -> bool {
+        remainder_bits <= 8 * std::mem::size_of::<Self::Remainder>() as u64
+    }
 
     // Default by quotient
 
@@ -108,8 +153,8 @@ pub trait Blocks {
     // fn runends(&self, quotient: u64) -> u64;
     // fn counts(&self, quotient: u64) -> u64;
 
-    fn slot(&self, quotient: u64) -> &Self::Remainder;
-    fn slot_mut(&mut self, quotient: u64) -> &mut Self::Remainder;
+    fn slot(&self, quotient: u64) -> Self::Remainder;
+    fn set_slot(&mut self, quotient: u64, value: Self::Remainder);
 
     fn is_occupied(&self, quotient: u64) -> bool;
     fn is_runend(&self, quotient: u64) -> bool;
@@ -126,11 +171,11 @@ pub trait Blocks {
 
     fn occupieds_by_block(&self, block: usize) -> u64;
     fn runends_by_block(&self, block: usize) -> u64;
-    // fn counts_by_block(&self, block: usize) -> u64;
+    fn counts_by_block(&self, block: usize) -> u64;
 
     // By block and slot
-    fn slot_by_block(&self, block: usize, slot: usize) -> &Self::Remainder;
-    fn slot_by_block_mut(&mut self, block: usize, slot: usize) -> &mut Self::Remainder;
+    fn slot_by_block(&self, block: usize, slot: usize) -> Self::Remainder;
+    fn set_slot_by_block(&mut self, block: usize, slot: usize, value: Self::Remainder);
 
     fn is_occupied_by_block(&self, block: usize, slot: usize) -> bool;
     fn is_runend_by_block(&self, block: usize, slot: usize) -> bool;
@@ -157,6 +202,40 @@ pub trait Blocks {
         }
     }
 
+    /// Returns the bitmap of the slots of `block` that belong to a run, from the offset of the
+    /// block and its occupieds and runends.
+    fn used_slots_by_block(&self, block: usize) -> u64 {
+        let offset = self.offset_by_block(block);
+        if offset >= SLOTS_PER_BLOCK as u64 {
+            return u64::MAX;
+        }
+        let occupieds = self.occupieds_by_block(block);
+        let runends = self.runends_by_block(block);
+        // The first offset slots end the runs of earlier quotients, and the runs of the
+        // quotients before the offset start right after them
+        let mut used = saturating_bitmask(offset);
+        let mut open_runs = (occupieds & saturating_bitmask(offset)).count_ones() as u64;
+        let mut from = offset;
+        let mut events = (occupieds | runends) & !saturating_bitmask(offset);
+        while events != 0 {
+            let slot = events.trailing_zeros() as u64;
+            events &= events - 1;
+            if open_runs > 0 {
+                used |= saturating_bitmask(slot) & !saturating_bitmask(from);
+            }
+            open_runs += (occupieds >> slot) & 1;
+            if open_runs > 0 {
+                used |= 1 << slot;
+            }
+            open_runs -= (runends >> slot) & 1;
+            from = slot + 1;
+        }
+        if open_runs > 0 {
+            used |= !saturating_bitmask(from);
+        }
+        used
+    }
+
     fn has_metadata_bits_set(&self, quotient: u64) -> bool {
         self.is_occupied(quotient) || self.is_runend(quotient) || self.is_count(quotient)
     }
@@ -188,10 +267,212 @@ pub trait Blocks {
         0
     }
 
+    /// Returns the first occupied quotient at or after `from_quotient`, if any.
+    fn find_next_occupied_slot(&self, from_quotient: u64) -> Option<u64> {
+        let (mut block_index, slot_index) = Self::split_quotient(from_quotient);
+        if block_index >= self.num_blocks() {
+            return None;
+        }
+        if let Some(slot) = ffsv(self.occupieds_by_block(block_index), slot_index as u64) {
+            return Some((block_index * SLOTS_PER_BLOCK) as u64 + slot);
+        }
+        block_index += 1;
+        while block_index < self.num_blocks() {
+            if let Some(slot) = ffs(self.occupieds_by_block(block_index)) {
+                return Some((block_index * SLOTS_PER_BLOCK) as u64 + slot);
+            }
+            block_index += 1;
+        }
+        None
+    }
+
+    /// Returns the first slot at or after `from_quotient` with its runend bit set.
+    /// Assumes such a slot exists, which holds for any slot inside a run.
+    fn find_next_runend(&self, from_quotient: u64) -> u64 {
+        let (mut block_index, slot_index) = Self::split_quotient(from_quotient);
+        let mut ignore = slot_index as u64;
+        loop {
+            if let Some(slot) = ffsv(self.runends_by_block(block_index), ignore) {
+                return (block_index * SLOTS_PER_BLOCK) as u64 + slot;
+            }
+            block_index += 1;
+            ignore = 0;
+        }
+    }
+
+    /// Width of a slot in bits, which is also the width of one counter digit.
+    fn slot_bits(&self) -> u64 {
+        8 * std::mem::size_of::<Self::Remainder>() as u64
+    }
+
+    /// Returns the number of counter slots needed after the remainder to store `count`.
+    /// A count of 1 is stored as the remainder alone.
+    fn counter_slots(&self, count: u64) -> u64 {
+        if count <= 1 {
+            0
+        } else {
+            (u64::BITS - count.leading_zeros()).div_ceil(self.slot_bits() as u32) as u64
+        }
+    }
+
+    /// Writes `remainder` at `index` followed by the counter slots of `count`, most significant
+    /// digit first. Runend bits are left to the caller.
+    /// Returns the number of slots written.
+    fn write_counter(&mut self, index: u64, remainder: Self::Remainder, count: u64) -> u64 {
+        let bits = self.slot_bits();
+        let digits = self.counter_slots(count);
+        self.set_slot(index, remainder);
+        self.set_count(index, false);
+        for digit in 1..=digits {
+            let shift = (digits - digit) * bits;
+            let value = count.unbounded_shr(shift as u32) & saturating_bitmask(bits);
+            self.set_slot(index + digit, Self::Remainder::from_u64(value));
+            self.set_count(index + digit, true);
+        }
+        digits + 1
+    }
+
+    /// Decodes a counter whose remainder is at `*quotient`, reading any number of counter
+    /// slots. Leaves `*quotient` at the last slot of the counter.
+    fn decode_multi_slot_counter(&self, quotient: &mut u64) -> (Self::Remainder, u64) {
+        let remainder = self.slot(*quotient);
+        if self.is_runend(*quotient) || !self.is_count(*quotient + 1) {
+            return (remainder, 1);
+        }
+        let bits = self.slot_bits() as u32;
+        let mut count = 0u64;
+        while !self.is_runend(*quotient) && self.is_count(*quotient + 1) {
+            *quotient += 1;
+            count = count.unbounded_shl(bits) | self.slot(*quotient).into();
+        }
+        (remainder, count)
+    }
+
+    /// Returns the number of slots in `from..=to` holding counter digits.
+    fn count_slots_between(&self, from: u64, to: u64) -> u64 {
+        let mut total = 0;
+        let mut quotient = from;
+        while quotient <= to {
+            let (block_index, slot_index) = Self::split_quotient(quotient);
+            let last = to.min(((block_index + 1) * SLOTS_PER_BLOCK) as u64 - 1);
+            let counts = self.counts_by_block(block_index) >> slot_index;
+            total += (counts & saturating_bitmask(last - quotient + 1)).count_ones() as u64;
+            quotient = last + 1;
+        }
+        total
+    }
+
+    /// Shifts slots right so that `index..index + n` is free, moving each cluster only as far
+    /// as needed to fill the next empty slots. Offsets are not updated.
+    /// Returns the last empty slot that was filled, or `None` without moving anything if the
+    /// clusters would be pushed past the last block.
+    fn make_room(&mut self, index: u64, n: u64) -> Option<u64> {
+        let num_slots = (self.num_blocks() * SLOTS_PER_BLOCK) as u64;
+        let mut empties = Vec::with_capacity(n as usize);
+        let mut from = index;
+        for _ in 0..n {
+            // Same walk as find_first_empty_slot, stopping at the end of the blocks
+            let mut empty = from;
+            loop {
+                if empty >= num_slots {
+                    return None;
+                }
+                let jump = self.offset_lower_bound(empty);
+                if jump == 0 {
+                    break;
+                }
+                empty += jump;
+            }
+            empties.push(empty);
+            from = empty + 1;
+        }
+        // Each segment between two empty slots moves by the number of empty slots after it
+        for k in (0..n as usize).rev() {
+            let segment_start = if k == 0 { index } else { empties[k - 1] + 1 };
+            let distance = n - k as u64;
+            for slot in (segment_start..empties[k]).rev() {
+                self.move_slot(slot, slot + distance);
+            }
+        }
+        Some(empties.last().copied().unwrap_or(index))
+    }
+
+    /// Copies the remainder, runend and count bits of slot `from` into slot `to`.
+    fn move_slot(&mut self, from: u64, to: u64) {
+        self.set_slot(to, self.slot(from));
+        self.set_runend(to, self.is_runend(from));
+        self.set_count(to, self.is_count(from));
+    }
+
+    /// Sets the offsets of blocks `first_block..=last_block` from the run ends of the blocks
+    /// before them. The runs must be consistent and the offset of `first_block - 1` correct.
+    fn recompute_offsets(&mut self, first_block: usize, last_block: usize) {
+        for block in first_block.max(1)..=last_block.min(self.num_blocks() - 1) {
+            let block_start = (block * SLOTS_PER_BLOCK) as u64;
+            let end_of_runs = self.run_end(block_start - 1) + 1;
+            *self.offset_by_block_mut(block) = end_of_runs - block_start;
+        }
+    }
+
+    /// Removes the `n` slots starting at `index`, which must belong to the run of `quotient`,
+    /// and shifts the rest of the cluster left to close the gap.
+    /// Clears the occupied bit of `quotient` if its run becomes empty.
+    fn remove_slots(&mut self, quotient: u64, index: u64, n: u64) {
+        let run_start = std::cmp::max(self.run_start(quotient), quotient);
+        let run_end = self.run_end(quotient);
+        if index == run_start && index + n == run_end + 1 {
+            self.set_occupied(quotient, false);
+        } else if index + n == run_end + 1 {
+            self.set_runend(index - 1, true);
+        }
+
+        // Rest of the run of quotient
+        let mut write = index;
+        for read in (index + n)..=run_end {
+            self.move_slot(read, write);
+            write += 1;
+        }
+
+        // Following runs move left, but never before their own quotient
+        let mut old_end = run_end;
+        let mut next_quotient = self.find_next_occupied_slot(quotient + 1);
+        while let Some(next) = next_quotient {
+            if next > old_end {
+                // This run and the ones after it are not shifted
+                break;
+            }
+            let start = old_end + 1;
+            let end = self.find_next_runend(start);
+            let new_start = std::cmp::max(next, write);
+            for gap in write..new_start {
+                self.set_runend(gap, false);
+                self.set_count(gap, false);
+            }
+            for read in start..=end {
+                self.move_slot(read, new_start + (read - start));
+            }
+            write = new_start + (end - start + 1);
+            old_end = end;
+            next_quotient = self.find_next_occupied_slot(next + 1);
+        }
+
+        for slot in write..=old_end {
+            self.set_runend(slot, false);
+            self.set_count(slot, false);
+        }
+        let (quotient_block, _) = Self::split_quotient(quotient);
+        let (last_block, _) = Self::split_quotient(old_end);
+        self.recompute_offsets(quotient_block + 1, last_block);
+    }
+
     // fn madvise_dont_need(&self, current_quotient: u64);
 
     fn num_blocks(&self) -> usize;
 
     /// Returns number of blocks in the CQF
     fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
@@ -0,0 +1,263 @@
+use std::ptr::Unique;
+
+use super::{Blocks, Offset};
+use crate::utils::saturating_bitmask;
+pub type Remainder = u64;
+
+/// Metadata words at the start of every block.
+/// The remainders follow as `remainder_bits` words holding 64 slots of `remainder_bits` bits.
+#[repr(C)]
+pub struct BlockHeader {
+    occupieds: u64,
+    runends: u64,
+    counts: u64,
+    offset: u64,
+}
+
+const HEADER_WORDS: usize = std::mem::size_of::<BlockHeader>() / std::mem::size_of::<u64>();
+
+/// Blocks whose remainder width is chosen at runtime, with slots packed across 64-bit words.
+pub struct PackedBlocks {
+    ptr: Unique<u64>,
+    len: usize,
+    remainder_bits: u64,
+}
+
+impl PackedBlocks {
+    pub fn new(ptr: *mut u8, len: usize, remainder_bits: u64) -> Self {
+        let ptr = unsafe { Unique::new_unchecked(ptr as *mut u64) };
+        Self {
+            ptr,
+            len,
+            remainder_bits,
+        }
+    }
+
+    #[inline(always)]
+    fn block_words(&self) -> usize {
+        HEADER_WORDS + self.remainder_bits as usize
+    }
+
+    #[inline(always)]
+    fn header(&self, block: usize) -> &BlockHeader {
+        assert!(block < self.len);
+        unsafe { &*(self.ptr.as_ptr().add(block * self.block_words()) as *const BlockHeader) }
+    }
+
+    #[inline(always)]
+    fn header_mut(&mut self, block: usize) -> &mut BlockHeader {
+        assert!(block < self.len);
+        unsafe { &mut *(self.ptr.as_ptr().add(block * self.block_words()) as *mut BlockHeader) }
+    }
+
+    #[inline(always)]
+    fn remainder_words(&self, block: usize) -> &[u64] {
+        assert!(block < self.len);
+        unsafe {
+            std::slice::from_raw_parts(
+                self.ptr
+                    .as_ptr()
+                    .add(block * self.block_words() + HEADER_WORDS),
+                self.remainder_bits as usize,
+            )
+        }
+    }
+
+    #[inline(always)]
+    fn remainder_words_mut(&mut self, block: usize) -> &mut [u64] {
+        assert!(block < self.len);
+        unsafe {
+            std::slice::from_raw_parts_mut(
+                self.ptr
+                    .as_ptr()
+                    .add(block * self.block_words() + HEADER_WORDS),
+                self.remainder_bits as usize,
+            )
+        }
+    }
+}
+
+impl Blocks for PackedBlocks {
+    type Remainder = Remainder;
+
+    fn bytes_needed(num_blocks: usize, remainder_bits: u64) -> usize {
+        num_blocks * (HEADER_WORDS + remainder_bits as usize) * std::mem::size_of::<u64>()
+    }
+
+    unsafe fn from_raw_parts(ptr: *mut u8, num_blocks: usize, remainder_bits: u64) -> Self {
+        Self::new(ptr, num_blocks, remainder_bits)
+    }
+
+    fn supports_remainder_bits(remainder_bits: u64) -> bool {
+        (1..=64).contains(&remainder_bits)
+    }
+
+    fn offset(&self, quotient: u64) -> Offset {
+        let (block_index, _) = Self::split_quotient(quotient);
+        self.offset_by_block(block_index)
+    }
+
+    #[inline(always)]
+    fn decode_counter(&self, quotient: &mut u64) -> (Self::Remainder, u64) {
+        self.decode_multi_slot_counter(quotient)
+    }
+
+    #[inline(always)]
+    fn offset_mut(&mut self, quotient: u64) -> &mut Offset {
+        let (block_index, _) = Self::split_quotient(quotient);
+        self.offset_by_block_mut(block_index)
+    }
+
+    #[inline(always)]
+    fn slot(&self, quotient: u64) -> Self::Remainder {
+        let (block_index, slot_index) = Self::split_quotient(quotient);
+        self.slot_by_block(block_index, slot_index)
+    }
+
+    #[inline(always)]
+    fn set_slot(&mut self, quotient: u64, value: Self::Remainder) {
+        let (block_index, slot_index) = Self::split_quotient(quotient);
+        self.set_slot_by_block(block_index, slot_index, value)
+    }
+
+    fn is_occupied(&self, quotient: u64) -> bool {
+        let (block_index, slot_index) = Self::split_quotient(quotient);
+        self.is_occupied_by_block(block_index, slot_index)
+    }
+
+    fn is_runend(&self, quotient: u64) -> bool {
+        let (block_index, slot_index) = Self::split_quotient(quotient);
+        self.is_runend_by_block(block_index, slot_index)
+    }
+
+    fn is_count(&self, quotient: u64) -> bool {
+        let (block_index, slot_index) = Self::split_quotient(quotient);
+        self.is_count_by_block(block_index, slot_index)
+    }
+
+    fn set_occupied(&mut self, quotient: u64, bit: bool) {
+        let (block_index, slot_index) = Self::split_quotient(quotient);
+        self.set_occupied_by_block(block_index, slot_index, bit)
+    }
+
+    fn set_runend(&mut self, quotient: u64, bit: bool) {
+        let (block_index, slot_index) = Self::split_quotient(quotient);
+        self.set_runend_by_block(block_index, slot_index, bit)
+    }
+
+    fn set_count(&mut self, quotient: u64, bit: bool) {
+        let (block_index, slot_index) = Self::split_quotient(quotient);
+        self.set_count_by_block(block_index, slot_index, bit)
+    }
+
+    #[inline(always)]
+    fn offset_by_block(&self, block: usize) -> Offset {
+        self.header(block).offset as Offset
+    }
+
+    #[inline(always)]
+    fn offset_by_block_mut(&mut self, block: usize) -> &mut Offset {
+        &mut self.header_mut(block).offset
+    }
+
+    #[inline(always)]
+    fn occupieds_by_block(&self, block: usize) -> u64 {
+        self.header(block).occupieds
+    }
+
+    #[inline(always)]
+    fn runends_by_block(&self, block: usize) -> u64 {
+        self.header(block).runends
+    }
+
+    #[inline(always)]
+    fn counts_by_block(&self, block: usize) -> u64 {
+        self.header(block).counts
+    }
+
+    #[inline(always)]
+    fn slot_by_block(&self, block: usize, slot: usize) -> Self::Remainder {
+        let bits = self.remainder_bits;
+        let words = self.remainder_words(block);
+        let bit = slot as u64 * bits;
+        let (word, shift) = ((bit / 64) as usize, bit % 64);
+        let mut value = words[word] >> shift;
+        if shift + bits > 64 {
+            // The slot straddles two words
+            value |= words[word + 1] << (64 - shift);
+        }
+        value & saturating_bitmask(bits)
+    }
+
+    #[inline(always)]
+    fn set_slot_by_block(&mut self, block: usize, slot: usize, value: Self::Remainder) {
+        let bits = self.remainder_bits;
+        let mask = saturating_bitmask(bits);
+        let value = value & mask;
+        let words = self.remainder_words_mut(block);
+        let bit = slot as u64 * bits;
+        let (word, shift) = ((bit / 64) as usize, bit % 64);
+        words[word] = (words[word] & !(mask << shift)) | (value << shift);
+        if shift + bits > 64 {
+            let spill = 64 - shift;
+            words[word + 1] = (words[word + 1] & !(mask >> spill)) | (value >> spill);
+        }
+    }
+
+    #[inline(always)]
+    fn is_occupied_by_block(&self, block: usize, slot: usize) -> bool {
+        self.header(block).occupieds & (1 << slot) != 0
+    }
+
+    #[inline(always)]
+    fn is_runend_by_block(&self, block: usize, slot: usize) -> bool {
+        self.header(block).runends & (1 << slot) != 0
+    }
+
+    #[inline(always)]
+    fn is_count_by_block(&self, block: usize, slot: usize) -> bool {
+        self.header(block).counts & (1 << slot) != 0
+    }
+
+    #[inline(always)]
+    fn set_occupied_by_block(&mut self, block: usize, slot: usize, bit: bool) {
+        if bit {
+            self.header_mut(block).occupieds |= 1 << slot;
+        } else {
+            self.header_mut(block).occupieds &= !(1 << slot);
+        }
+    }
+
+    #[inline(always)]
+    fn set_runend_by_block(&mut self, block: usize, slot: usize, bit: bool) {
+        if bit {
+            self.header_mut(block).runends |= 1 << slot;
+        } else {
+            self.header_mut(block).runends &= !(1 << slot);
+        }
+    }
+
+    #[inline(always)]
+    fn set_count_by_block(&mut self, block: usize, slot: usize, bit: bool) {
+        if bit {
+            self.header_mut(block).counts |= 1 << slot;
+        } else {
+            self.header_mut(block).counts &= !(1 << slot);
+        }
+    }
+
+    /// Counter digits are as wide as the packed remainders, so with narrow remainders the
+    /// largest single-slot count is small, e.g. 3 for 2-bit remainders.
+    fn slot_bits(&self) -> u64 {
+        self.remainder_bits
+    }
+
+    #[inline(always)]
+    fn num_blocks(&self) -> usize {
+        self.len
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
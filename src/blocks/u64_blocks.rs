@@ -31,10 +31,14 @@ impl U64Blocks {
 impl Blocks for U64Blocks {
     type Remainder = Remainder;
 
-    fn bytes_needed(num_blocks: usize) -> usize {
+    fn bytes_needed(num_blocks: usize, _remainder_bits: u64) -> usize {
         num_blocks * std::mem::size_of::<Block>()
     }
 
+    unsafe fn from_raw_parts(ptr: *mut u8, num_blocks: usize, _remainder_bits: u64) -> Self {
+        Self::new(ptr, num_blocks)
+    }
+
     fn offset(&self, quotient: u64) -> Offset {
         let (block_index, _) = Self::split_quotient(quotient);
         self.offset_by_block(block_index)
@@ -43,13 +47,13 @@ impl Blocks for U64Blocks {
     #[inline(always)]
     fn decode_counter(&self, quotient: &mut u64) -> (Self::Remainder, u64) {
         let (block_index, slot_index) = Self::split_quotient(*quotient);
-        let remainder = *self.slot_by_block(block_index, slot_index);
+        let remainder = self.slot_by_block(block_index, slot_index);
         let count = if self.is_runend(*quotient) || !self.is_count(*quotient + 1) {
             1
         } else {
             // Only works for u64
             *quotient += 1;
-            *self.slot(*quotient)
+            self.slot(*quotient)
             // let mut qptr = *quotient + 1;
             // let mut c: u64 = 0;
             // while self.is_count(qptr) {
@@ -88,15 +92,15 @@ impl Blocks for U64Blocks {
     // }
 
     #[inline(always)]
-    fn slot(&self, quotient: u64) -> &Self::Remainder {
+    fn slot(&self, quotient: u64) -> Self::Remainder {
         let (block_index, slot_index) = Self::split_quotient(quotient);
         self.slot_by_block(block_index, slot_index)
     }
 
     #[inline(always)]
-    fn slot_mut(&mut self, quotient: u64) -> &mut Self::Remainder {
+    fn set_slot(&mut self, quotient: u64, value: Self::Remainder) {
         let (block_index, slot_index) = Self::split_quotient(quotient);
-        self.slot_by_block_mut(block_index, slot_index)
+        self.set_slot_by_block(block_index, slot_index, value)
     }
 
     fn is_occupied(&self, quotient: u64) -> bool {
@@ -149,19 +153,19 @@ impl Blocks for U64Blocks {
         self[block].runends
     }
 
-    // #[inline(always)]
-    // fn counts_by_block(&self, block: usize) -> u64 {
-    //     self[block].counts
-    // }
+    #[inline(always)]
+    fn counts_by_block(&self, block: usize) -> u64 {
+        self[block].counts
+    }
 
     #[inline(always)]
-    fn slot_by_block(&self, block: usize, slot: usize) -> &Self::Remainder {
-        &self[block].remainders[slot]
+    fn slot_by_block(&self, block: usize, slot: usize) -> Self::Remainder {
+        self[block].remainders[slot]
     }
 
     #[inline(always)]
-    fn slot_by_block_mut(&mut self, block: usize, slot: usize) -> &mut Self::Remainder {
-        &mut self[block].remainders[slot]
+    fn set_slot_by_block(&mut self, block: usize, slot: usize, value: Self::Remainder) {
+        self[block].remainders[slot] = value;
     }
 
     #[inline(always)]
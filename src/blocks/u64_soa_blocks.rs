@@ -1,6 +1,6 @@
-use std::ptr::Unique;
+// use libc::c_void;
 
-use libc::c_void;
+use std::ptr::Unique;
 
 use super::{Blocks, Offset};
 use crate::SLOTS_PER_BLOCK;
@@ -23,7 +23,7 @@ impl U64SoaBlocks {
             panic!("ptr is null");
         }
         let ptr_metadata = unsafe { Unique::new_unchecked(ptr as *mut BlockMetadata) };
-        let ptr_blocks = unsafe { ptr_metadata.as_ptr().offset(len as isize) } as *mut Block;
+        let ptr_blocks = unsafe { ptr_metadata.as_ptr().add(len) } as *mut Block;
         let ptr_blocks = unsafe { Unique::new_unchecked(ptr_blocks) };
         Self {
             ptr_metadata,
@@ -36,38 +36,26 @@ impl U64SoaBlocks {
 impl Blocks for U64SoaBlocks {
     type Remainder = Remainder;
 
-    fn bytes_needed(num_blocks: usize) -> usize {
+    fn bytes_needed(num_blocks: usize, _remainder_bits: u64) -> usize {
         let mut size = 0;
         size += num_blocks * std::mem::size_of::<Block>();
         size += num_blocks * std::mem::size_of::<BlockMetadata>();
         size
     }
 
+    unsafe fn from_raw_parts(ptr: *mut u8, num_blocks: usize, _remainder_bits: u64) -> Self {
+        Self::new(ptr, num_blocks)
+    }
+
     fn offset(&self, quotient: u64) -> Offset {
         let (block_index, _) = Self::split_quotient(quotient);
         self.offset_by_block(block_index)
     }
 
     #[inline(always)]
-    fn decode_counter(&self, quotient: &mut u64, remainder: &mut Self::Remainder, count: &mut u64) {
-        let (block_index, slot_index) = Self::split_quotient(*quotient);
-        *remainder = *self.slot_by_block(block_index, slot_index);
-        if self.is_runend(*quotient) || !self.is_count(*quotient + 1) {
-            *count = 1;
-        } else {
-            // Only works for u64
-            *count = *self.slot(*quotient + 1);
-            *quotient += 1;
-            // let mut qptr = *quotient + 1;
-            // let mut c: u64 = 0;
-            // while self.is_count(qptr) {
-            //     c <<= Self::Remainder::BITS;
-            //     c |= self.slot(qptr);
-            //     qptr += 1;
-            // }
-            // *quotient = qptr;
-            // *count = c;
-        }
+    fn decode_counter(&self, quotient: &mut u64) -> (Self::Remainder, u64) {
+        // A u64 count always fits in one counter slot
+        self.decode_multi_slot_counter(quotient)
     }
 
     #[inline(always)]
@@ -77,33 +65,15 @@ impl Blocks for U64SoaBlocks {
     }
 
     #[inline(always)]
-    fn occupieds(&self, quotient: u64) -> u64 {
-        let (block_index, _) = Self::split_quotient(quotient);
-        self.occupieds_by_block(block_index)
-    }
-
-    #[inline(always)]
-    fn runends(&self, quotient: u64) -> u64 {
-        let (block_index, _) = Self::split_quotient(quotient);
-        self.runends_by_block(block_index)
-    }
-
-    #[inline(always)]
-    fn counts(&self, quotient: u64) -> u64 {
-        let (block_index, _) = Self::split_quotient(quotient);
-        self.counts_by_block(block_index)
-    }
-
-    #[inline(always)]
-    fn slot(&self, quotient: u64) -> &Self::Remainder {
+    fn slot(&self, quotient: u64) -> Self::Remainder {
         let (block_index, slot_index) = Self::split_quotient(quotient);
-        &self.slot_by_block(block_index, slot_index)
+        self.slot_by_block(block_index, slot_index)
     }
 
     #[inline(always)]
-    fn slot_mut(&mut self, quotient: u64) -> &mut Self::Remainder {
+    fn set_slot(&mut self, quotient: u64, value: Self::Remainder) {
         let (block_index, slot_index) = Self::split_quotient(quotient);
-        self.slot_by_block_mut(block_index, slot_index)
+        self.set_slot_by_block(block_index, slot_index, value)
     }
 
     fn is_occupied(&self, quotient: u64) -> bool {
@@ -162,13 +132,13 @@ impl Blocks for U64SoaBlocks {
     }
 
     #[inline(always)]
-    fn slot_by_block(&self, block: usize, slot: usize) -> &Self::Remainder {
-        &self.block()[block].remainders[slot]
+    fn slot_by_block(&self, block: usize, slot: usize) -> Self::Remainder {
+        self.block()[block].remainders[slot]
     }
 
     #[inline(always)]
-    fn slot_by_block_mut(&mut self, block: usize, slot: usize) -> &mut Self::Remainder {
-        &mut self.block_mut()[block].remainders[slot]
+    fn set_slot_by_block(&mut self, block: usize, slot: usize, value: Self::Remainder) {
+        self.block_mut()[block].remainders[slot] = value;
     }
 
     #[inline(always)]
@@ -218,19 +188,19 @@ impl Blocks for U64SoaBlocks {
         self.len
     }
 
-    fn madvise_dont_need(&self, current_quotient: u64) {
-        let ptr_start = self.ptr_blocks.as_ptr() as *mut c_void;
-        let aligned_ptr_start = unsafe { ptr_start.offset(ptr_start.align_offset(4096) as isize) };
-        let ptr_end =
-            unsafe { (self.slot(current_quotient) as *const Self::Remainder).offset(-4096) };
-        if ptr_end as usize > aligned_ptr_start as usize {
-            let len = ptr_end as usize - aligned_ptr_start as usize;
-            let madv_result = unsafe { libc::madvise(aligned_ptr_start, len, libc::MADV_DONTNEED) };
-            if madv_result != 0 {
-                panic!("madvise failed: {}", madv_result);
-            }
-        }
-    }
+    // fn madvise_dont_need(&self, current_quotient: u64) {
+    //     let ptr_start = self.ptr_blocks.as_ptr() as *mut c_void;
+    //     let aligned_ptr_start = unsafe { ptr_start.offset(ptr_start.align_offset(4096) as isize) };
+    //     let ptr_end =
+    //         unsafe { (self.slot(current_quotient) as *const Self::Remainder).offset(-4096) };
+    //     if ptr_end as usize > aligned_ptr_start as usize {
+    //         let len = ptr_end as usize - aligned_ptr_start as usize;
+    //         let madv_result = unsafe { libc::madvise(aligned_ptr_start, len, libc::MADV_DONTNEED) };
+    //         if madv_result != 0 {
+    //             panic!("madvise failed: {}", madv_result);
+    //         }
+    //     }
+    // }
 
     fn len(&self) -> usize {
         self.len
@@ -255,9 +225,12 @@ impl U64SoaBlocks {
     }
 }
 
+/// Metadata bitvectors of a block, stored apart from the remainders so scans that only look
+/// at the metadata touch a quarter of the memory.
+#[repr(C)]
 pub struct BlockMetadata {
     occupieds: u64,
     runends: u64,
     counts: u64,
-    offset: u16,
+    offset: u64,
 }
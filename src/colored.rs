@@ -0,0 +1,232 @@
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+
+use crate::kmer::{self, Kmers};
+use crate::{CountingQuotientFilter, CqfError, CqfMap, CqfMerge, MergedCqfIter};
+
+/// Table of color classes: the distinct sets of samples that share a k-mer, as bitvectors.
+/// Class IDs are given in the order the classes are first seen.
+#[derive(Debug, Clone, Default)]
+pub struct ColorClasses {
+    num_samples: usize,
+    /// Number of u64 words in a bitvector
+    words: usize,
+    /// Bitvectors of all classes, `words` words each, in ID order
+    bits: Vec<u64>,
+    /// Last class ID with each bitvector hash
+    ids: HashMap<u64, u64>,
+    /// For each class, the previous class ID with the same bitvector hash
+    same_hash: Vec<Option<u64>>,
+    hasher: RandomState,
+}
+
+impl ColorClasses {
+    pub fn new(num_samples: usize) -> Self {
+        Self {
+            num_samples,
+            words: num_samples.div_ceil(64),
+            bits: Vec::new(),
+            ids: HashMap::new(),
+            same_hash: Vec::new(),
+            hasher: RandomState::new(),
+        }
+    }
+
+    pub fn num_samples(&self) -> usize {
+        self.num_samples
+    }
+
+    /// Returns the number of color classes.
+    pub fn len(&self) -> usize {
+        self.same_hash.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the ID of the class with the samples set in `bitvector`, adding it if needed.
+    pub fn intern(&mut self, bitvector: &[u64]) -> u64 {
+        let hash = self.hasher.hash_one(bitvector);
+        let mut candidate = self.ids.get(&hash).copied();
+        while let Some(id) = candidate {
+            if self.get(id) == Some(bitvector) {
+                return id;
+            }
+            candidate = self.same_hash[id as usize];
+        }
+        let id = self.len() as u64;
+        self.bits.extend_from_slice(bitvector);
+        self.same_hash.push(self.ids.insert(hash, id));
+        id
+    }
+
+    /// Returns the bitvector of class `id`, where bit `i % 64` of word `i / 64` is set if
+    /// sample `i` is in the class.
+    pub fn get(&self, id: u64) -> Option<&[u64]> {
+        let start = usize::try_from(id).ok()?.checked_mul(self.words)?;
+        self.bits.get(start..start + self.words)
+    }
+
+    /// Returns the samples of class `id`, in increasing order.
+    pub fn samples(&self, id: u64) -> Vec<usize> {
+        let Some(bitvector) = self.get(id) else {
+            return Vec::new();
+        };
+        let mut samples = Vec::new();
+        for (i, &word) in bitvector.iter().enumerate() {
+            let mut word = word;
+            while word != 0 {
+                samples.push(i * 64 + word.trailing_zeros() as usize);
+                word &= word - 1;
+            }
+        }
+        samples
+    }
+
+    /// Returns whether `sample` is in class `id`.
+    pub fn contains(&self, id: u64, sample: usize) -> bool {
+        self.get(id)
+            .and_then(|bitvector| bitvector.get(sample / 64))
+            .is_some_and(|word| word >> (sample % 64) & 1 == 1)
+    }
+}
+
+/// Colored index of many samples, as in Mantis: maps each k-mer to the color class of the
+/// samples that hold it.
+///
+/// The k-mers are stored in a [`CqfMap`] from their hash to a class ID, and the classes in a
+/// [`ColorClasses`] table, so samples sharing most of their k-mers share a few classes.
+/// Built from one CQF per sample of canonical k-mers, all with the same hasher and number of
+/// hash bits as the CQF of the index, and queried with k-mers in either orientation. Like the
+/// queries of a CQF, a k-mer can get the class of another k-mer with the same hash.
+pub struct ColoredCqf<C: CountingQuotientFilter> {
+    map: CqfMap<C>,
+    classes: ColorClasses,
+    k: usize,
+}
+
+impl<C: CountingQuotientFilter> ColoredCqf<C> {
+    /// Builds the index of the k-mers of `k` bases of `samples` into `cqf`, which must be empty.
+    /// See [`build_by`](Self::build_by).
+    pub fn build<S: CountingQuotientFilter>(
+        samples: &[S],
+        k: usize,
+        cqf: C,
+    ) -> Result<Self, CqfError> {
+        Self::build_by(samples, k, cqf, |_, _, _| true)
+    }
+
+    /// Builds the index of the k-mers of `k` bases of `samples` into `cqf`, which must be
+    /// empty, keeping
+    /// the k-mers of sample `i` for which `keep(i, count, hash)` is true, such as those seen
+    /// at least twice. The samples are merged in one pass over their entries, in hash order
+    /// (see [`MergedCqfIter`]).
+    /// Returns [`CqfError::InvalidArguments`] if `k` is not in `1..=32`, a sample does not use
+    /// the same number of hash bits as `cqf` or `cqf` holds entries, or [`CqfError::Filled`] if
+    /// the k-mers do not fit in `cqf`.
+    pub fn build_by<S: CountingQuotientFilter, F: FnMut(usize, u64, u64) -> bool>(
+        samples: &[S],
+        k: usize,
+        cqf: C,
+        mut keep: F,
+    ) -> Result<Self, CqfError> {
+        let hash_bits = cqf.quotient_bits() + cqf.remainder_bits();
+        if !(1..=kmer::MAX_K).contains(&k)
+            || samples
+                .iter()
+                .any(|sample| sample.quotient_bits() + sample.remainder_bits() != hash_bits)
+        {
+            return Err(CqfError::InvalidArguments);
+        }
+
+        let mut classes = ColorClasses::new(samples.len());
+        let mut merged = MergedCqfIter::new(samples.iter().map(|sample| sample.iter())).peekable();
+        let mut entries = Vec::new();
+        let mut bitvector = vec![0; classes.words];
+        while let Some(&(_, (_, hash))) = merged.peek() {
+            bitvector.fill(0);
+            while let Some((i, (count, _))) = merged.next_if(|&(_, (_, next))| next == hash) {
+                if keep(i, count, hash) {
+                    bitvector[i / 64] |= 1 << (i % 64);
+                }
+            }
+            if bitvector.iter().any(|&word| word != 0) {
+                // Class IDs are stored as counts, shifted by one as in CqfMap
+                entries.push((classes.intern(&bitvector) + 1, hash));
+            }
+        }
+
+        let needed: u64 = entries
+            .iter()
+            .map(|&(count, _)| cqf.entry_slots(count))
+            .sum();
+        if needed > cqf.max_occupied_slots() {
+            return Err(CqfError::Filled);
+        }
+        let value_bits = (u64::BITS - classes.len().saturating_sub(1).leading_zeros()).max(1);
+        let mut map = CqfMap::new(cqf, value_bits as u64)?;
+        CqfMerge::insert_sorted(entries.into_iter(), map.cqf_mut())?;
+        Ok(Self { map, classes, k })
+    }
+
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    pub fn num_samples(&self) -> usize {
+        self.classes.num_samples()
+    }
+
+    /// Returns the color class ID of the k-mer `kmer`, in either orientation, if it is in any
+    /// sample.
+    pub fn color_class(&self, kmer: u64) -> Option<u64> {
+        self.map.get(kmer::canonical(kmer, self.k))
+    }
+
+    /// Returns the samples holding the k-mer `kmer`, in either orientation, in increasing order.
+    pub fn samples(&self, kmer: u64) -> Vec<usize> {
+        self.color_class(kmer)
+            .map(|id| self.classes.samples(id))
+            .unwrap_or_default()
+    }
+
+    /// Queries the canonical k-mers of the DNA sequence `seq` in one batch, as counted by
+    /// [`insert_sequence`](CountingQuotientFilter::insert_sequence).
+    /// Returns the number of those k-mers held by each sample, indexed by sample.
+    pub fn query_sequence(&self, seq: &[u8]) -> Vec<u64> {
+        let cqf = self.map.cqf();
+        let hashes: Vec<u64> = Kmers::new(seq, self.k)
+            .flatten()
+            .map(|kmer| cqf.calc_hash(kmer))
+            .collect();
+        // Count the classes first, as k-mers of a sequence mostly share a few classes
+        let mut class_hits: HashMap<u64, u64> = HashMap::new();
+        for count in cqf.query_batch_by_hash(&hashes) {
+            if let Some(id) = count.checked_sub(1) {
+                *class_hits.entry(id).or_insert(0) += 1;
+            }
+        }
+        let mut hits = vec![0; self.num_samples()];
+        for (id, class_count) in class_hits {
+            for sample in self.classes.samples(id) {
+                hits[sample] += class_count;
+            }
+        }
+        hits
+    }
+
+    /// Returns the map from k-mer hashes to color class IDs.
+    pub fn map(&self) -> &CqfMap<C> {
+        &self.map
+    }
+
+    pub fn classes(&self) -> &ColorClasses {
+        &self.classes
+    }
+
+    pub fn into_parts(self) -> (CqfMap<C>, ColorClasses) {
+        (self.map, self.classes)
+    }
+}
@@ -1,7 +1,19 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 use std::fs::File;
 use std::hash::{BuildHasher, Hash};
+use std::ops::{Bound, RangeBounds};
 use std::ptr::{NonNull, Unique};
 
+use crate::blocks::packed_blocks::PackedBlocks;
+use crate::blocks::slot_blocks::{U16Blocks, U32Blocks, U8Blocks};
+use crate::blocks::u64_blocks::U64Blocks;
+use crate::blocks::u64_soa_blocks::U64SoaBlocks;
+use crate::histogram::CountHistogram;
+use crate::kmer::{self, Direction, KmerCoverage, UnitigWalk};
+use crate::similarity::Similarity;
+use crate::stats::CqfStats;
+use crate::top_k::TopK;
 use crate::SLOTS_PER_BLOCK;
 
 /// Owns Metadata (through a pointer)
@@ -39,6 +51,8 @@ struct Metadata {
     pub quotient_bits: u64,
     pub remainder_bits: u64,
     pub invertable: u64,
+    // No longer maintained: inserts fail on the load factor or when a shift runs off the
+    // table instead. Both fields are kept so files keep their layout.
     pub largest_offset: u64,
     pub largest_possible_offset: u64,
 }
@@ -56,10 +70,22 @@ impl std::ops::DerefMut for MetadataWrapper {
     }
 }
 
+/// Returns the number of slots of a CQF with `quotient_bits`, counting the slots past the last
+/// quotient that runs can spill into.
+fn num_real_slots(quotient_bits: u64) -> u64 {
+    let num_slots: u64 = 1u64 << quotient_bits;
+    (num_slots as f64 + 10_f64 * (num_slots as f64).sqrt()) as u64
+}
+
+/// Returns the number of slots that can be filled in a CQF of `num_real_slots` slots.
+fn max_occupied_slots(num_real_slots: u64) -> u64 {
+    ((num_real_slots as f64) * MAX_LOAD_FACTOR) as u64
+}
+
 impl Metadata {
     fn new(quotient_bits: u64, hash_bits: u64, invertable: bool) -> Self {
         let num_slots: u64 = 1u64 << quotient_bits;
-        let num_real_slots = (num_slots as f64 + 10_f64 * (num_slots as f64).sqrt()) as u64;
+        let num_real_slots = num_real_slots(quotient_bits);
         let num_blocks = (num_real_slots + SLOTS_PER_BLOCK as u64 - 1) / SLOTS_PER_BLOCK as u64;
         let remainder_bits = hash_bits - quotient_bits;
         let invertable = if invertable { 1 } else { 0 };
@@ -88,11 +114,15 @@ impl Metadata {
     }
 }
 
+/// Fraction of the slots that can be filled before inserts return [`CqfError::Filled`].
+pub const MAX_LOAD_FACTOR: f64 = 0.80;
+
 /// RuntimeData for the CQF
 struct RuntimeData<H: BuildHasher> {
     pub file: Option<File>,
     pub hasher: H,
     pub max_occupied_slots: u64,
+    pub overflow_policy: OverflowPolicy,
 }
 
 impl<H: BuildHasher> RuntimeData<H> {
@@ -100,11 +130,25 @@ impl<H: BuildHasher> RuntimeData<H> {
         Self {
             file,
             hasher,
-            max_occupied_slots: ((num_real_slots as f64) * 0.80) as u64,
+            max_occupied_slots: max_occupied_slots(num_real_slots),
+            overflow_policy: OverflowPolicy::default(),
         }
     }
 }
 
+/// What a CQF does with a count that does not fit in its counters.
+/// The policy is not stored in the file, CQFs start with [`OverflowPolicy::Spill`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    /// Large counts take extra counter slots, up to `u64::MAX`.
+    #[default]
+    Spill,
+    /// Counts are capped at the largest count that fits in one counter slot.
+    Saturate,
+    /// Counts that do not fit in one counter slot are rejected with [`CqfError::CountOverflow`].
+    Error,
+}
+
 #[derive(Debug)]
 pub enum CqfError {
     InvalidArguments,
@@ -113,11 +157,16 @@ pub enum CqfError {
     InvalidFile,
     InvalidSize,
     Filled,
+    CountOverflow,
+    CountUnderflow,
 }
 
 pub trait CountingQuotientFilter: IntoIterator + Sized {
     type Hasher: BuildHasher;
     type Remainder: Copy + Clone + Default + std::fmt::Debug + Into<u64>;
+    type RefIterator<'a>: CqfIteratorImpl
+    where
+        Self: 'a;
 
     /// Makes a new in-memory CQF.
     fn new(
@@ -140,8 +189,8 @@ pub trait CountingQuotientFilter: IntoIterator + Sized {
     fn open_file(hasher: Self::Hasher, file: File) -> Result<Self, CqfError>;
 
     /// Inserts an item-count pair into the CQF.
-    /// Returns Ok(()) on successful insert, or a CqfError.
-    fn insert<Item: Hash>(&mut self, item: Item, count: u64) -> Result<(), CqfError> {
+    /// Returns the new count of item on successful insert, or a CqfError.
+    fn insert<Item: Hash>(&mut self, item: Item, count: u64) -> Result<u64, CqfError> {
         let hash = self.calc_hash(item);
         self.insert_by_hash(hash, count)
     }
@@ -152,6 +201,35 @@ pub trait CountingQuotientFilter: IntoIterator + Sized {
         (self.query_by_hash(hash), hash)
     }
 
+    /// Returns the (count, hash) of each item, in the order of `items`.
+    /// See [`query_batch_by_hash`](Self::query_batch_by_hash).
+    fn query_batch<Item: Hash>(&self, items: impl IntoIterator<Item = Item>) -> Vec<(u64, u64)> {
+        let hashes: Vec<u64> = items.into_iter().map(|item| self.calc_hash(item)).collect();
+        self.query_batch_by_hash(&hashes)
+            .into_iter()
+            .zip(hashes)
+            .collect()
+    }
+
+    /// Returns the count of each hash, in the order of `hashes`.
+    /// The lookups are made in increasing hash order, so nearby quotients are probed together,
+    /// and repeated hashes are looked up once.
+    fn query_batch_by_hash(&self, hashes: &[u64]) -> Vec<u64> {
+        let mut order: Vec<usize> = (0..hashes.len()).collect();
+        order.sort_unstable_by_key(|&i| hashes[i]);
+        let mut counts = vec![0; hashes.len()];
+        let mut last = None;
+        for i in order {
+            let count = match last {
+                Some((hash, count)) if hash == hashes[i] => count,
+                _ => self.query_by_hash(hashes[i]),
+            };
+            counts[i] = count;
+            last = Some((hashes[i], count));
+        }
+        counts
+    }
+
     /// Sets the count of item in the CQF.
     /// Inserts item into the CQF if it was not already present.
     /// Returns Ok(()) on success, or a CqfError.
@@ -163,8 +241,109 @@ pub trait CountingQuotientFilter: IntoIterator + Sized {
         // self.set_count_by_hash(hash, count)
         match self.set_count_by_hash(hash, count) {
             Ok(_) => Ok(()),
-            Err(_) => self.insert_by_hash(hash, count),
+            Err(CqfError::InvalidArguments) => self.insert_by_hash(hash, count).map(|_| ()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Adds `delta` to the count of item, removing item when its count reaches 0.
+    /// Returns the new count of item, or [`CqfError::CountUnderflow`] without changing the CQF
+    /// if the count would go below 0.
+    fn apply_delta<Item: Hash>(&mut self, item: Item, delta: i64) -> Result<u64, CqfError> {
+        let hash = self.calc_hash(item);
+        self.apply_delta_by_hash(hash, delta)
+    }
+
+    fn apply_delta_by_hash(&mut self, hash: u64, delta: i64) -> Result<u64, CqfError> {
+        if delta >= 0 {
+            return self.insert_by_hash(hash, delta as u64);
         }
+        let count = self
+            .query_by_hash(hash)
+            .checked_sub(delta.unsigned_abs())
+            .ok_or(CqfError::CountUnderflow)?;
+        self.set_count_by_hash(hash, count)?;
+        Ok(count)
+    }
+
+    /// Applies a batch of (item, delta) pairs, as [`apply_delta`](Self::apply_delta) would.
+    /// The deltas of an item are summed first, and the whole batch is checked before any
+    /// change: it is rejected with [`CqfError::CountUnderflow`] if a count would go below 0,
+    /// [`CqfError::CountOverflow`] if a count would not fit, or [`CqfError::Filled`] if the
+    /// new counts need more slots than the CQF has free.
+    /// Decreases are applied before increases, so the slots they free can be reused.
+    /// If a run still cannot grow because every slot past it is in use, the updates applied so
+    /// far are undone and [`CqfError::Filled`] is returned.
+    fn apply_deltas<Item: Hash>(
+        &mut self,
+        deltas: impl IntoIterator<Item = (Item, i64)>,
+    ) -> Result<(), CqfError> {
+        let mut deltas: Vec<(u64, i128)> = deltas
+            .into_iter()
+            .map(|(item, delta)| (self.calc_hash(item), delta as i128))
+            .collect();
+        deltas.sort_unstable_by_key(|&(hash, _)| hash);
+        deltas.dedup_by(|(hash, delta), (kept_hash, kept_delta)| {
+            let same = hash == kept_hash;
+            if same {
+                *kept_delta += *delta;
+            }
+            same
+        });
+
+        let slots = |count| {
+            if count == 0 {
+                0
+            } else {
+                self.entry_slots(count)
+            }
+        };
+        let mut updates = Vec::with_capacity(deltas.len());
+        let (mut grown, mut freed) = (0, 0);
+        for (hash, delta) in deltas {
+            let count = self.query_by_hash(hash);
+            let new_count = count as i128 + delta;
+            if new_count < 0 {
+                return Err(CqfError::CountUnderflow);
+            }
+            let new_count = self.fit_count(u64::try_from(new_count).ok())?;
+            if new_count != count {
+                grown += slots(new_count).saturating_sub(slots(count));
+                freed += slots(count).saturating_sub(slots(new_count));
+                updates.push((hash, count, new_count));
+            }
+        }
+        if self.occupied_slots() + grown - freed > self.max_occupied_slots() {
+            return Err(CqfError::Filled);
+        }
+
+        // Stable, so decreases come first and each half stays in hash order
+        updates.sort_by_key(|&(_, count, new_count)| new_count > count);
+        for (applied, &(hash, count, new_count)) in updates.iter().enumerate() {
+            let result = if count == 0 {
+                self.insert_by_hash(hash, new_count).map(|_| ())
+            } else {
+                self.set_count_by_hash(hash, new_count)
+            };
+            if let Err(err) = result {
+                // A run can still fail to grow when every slot past it is in use. Each step
+                // back restores a layout the CQF held before, so undoing cannot fail, and
+                // spilling keeps old counts that a policy set since then would reject.
+                let policy = self.overflow_policy();
+                self.set_overflow_policy(OverflowPolicy::Spill);
+                for &(hash, count, new_count) in updates[..applied].iter().rev() {
+                    let restored = if new_count == 0 {
+                        self.insert_by_hash(hash, count).map(|_| ())
+                    } else {
+                        self.set_count_by_hash(hash, count)
+                    };
+                    restored.expect("an earlier layout always fits");
+                }
+                self.set_overflow_policy(policy);
+                return Err(err);
+            }
+        }
+        Ok(())
     }
 
     fn quotient_bits(&self) -> u64;
@@ -173,7 +352,221 @@ pub trait CountingQuotientFilter: IntoIterator + Sized {
 
     // fn set_count_cb<Item: Hash, F: FnMut(u64) -> u64>(&mut self, item: Item, count: u64, cb: F) -> Result<u64, CqfError>;
 
-    // fn iter(&self) -> Self::CqfIterator;
+    /// Returns an iterator over the (count, hash) pairs of the CQF, in increasing hash order.
+    fn iter(&self) -> Self::RefIterator<'_>;
+
+    /// Returns an iterator over the (count, hash) pairs whose quotient is at least `quotient`,
+    /// in increasing hash order.
+    fn iter_from(&self, quotient: u64) -> Self::RefIterator<'_>;
+
+    /// Returns the `k` entries with the largest counts as (hash, count) pairs,
+    /// ordered from most to least frequent.
+    fn top_k(&self, k: usize) -> Vec<(u64, u64)> {
+        crate::top_k::top_k(self.iter(), k)
+    }
+
+    /// Returns the number of distinct entries and the sum of their counts over the hashes
+    /// in `range`. Only the runs from the start of the range are walked, skipping empty blocks.
+    /// With [`BuildIdentityHasher`](crate::BuildIdentityHasher), hashes are keys and this counts
+    /// a range of keys.
+    fn range_count<R: RangeBounds<u64>>(&self, range: R) -> (u64, u64) {
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => match start.checked_add(1) {
+                Some(start) => start,
+                None => return (0, 0),
+            },
+            Bound::Unbounded => 0,
+        };
+        if start.checked_shr((self.quotient_bits() + self.remainder_bits()) as u32) > Some(0) {
+            // Past the largest hash
+            return (0, 0);
+        }
+        let (quotient, _) = self.quotient_remainder_from_hash(start);
+        let (mut distinct, mut total) = (0, 0);
+        for (count, hash) in self.iter_from(quotient) {
+            if hash < start {
+                continue;
+            }
+            if !range.contains(&hash) {
+                break;
+            }
+            distinct += 1;
+            total += count;
+        }
+        (distinct, total)
+    }
+
+    /// Returns the number of distinct entries and the sum of their counts over the hashes
+    /// below `hash`, that is the rank of `hash` among the entries. The slots before the run of
+    /// `hash` are counted from the block metadata, decoding only the entries counted more than
+    /// once.
+    fn rank(&self, hash: u64) -> (u64, u64);
+
+    /// Returns the bottom-`k` MinHash sketch of the CQF: its `k` smallest hashes, in increasing
+    /// order. Sketches of CQFs with the same hasher can be compared to estimate their similarity.
+    fn sketch(&self, k: usize) -> Vec<u64> {
+        self.iter().take(k).map(|(_, hash)| hash).collect()
+    }
+
+    /// Returns `n` distinct entries chosen uniformly at random as (count, hash) pairs,
+    /// in increasing hash order, or all the entries if there are fewer than `n`.
+    /// The same `seed` gives the same sample.
+    fn sample(&self, n: usize, seed: u64) -> Vec<(u64, u64)>;
+
+    /// Returns the histogram of counts of the CQF, with bins up to `max_bin`.
+    fn count_histogram(&self, max_bin: u64) -> CountHistogram {
+        CountHistogram::from_counts(self.iter(), max_bin)
+    }
+
+    /// Same as [`count_histogram`](Self::count_histogram), splitting the quotients
+    /// over `threads` threads.
+    fn count_histogram_par(&self, max_bin: u64, threads: usize) -> CountHistogram
+    where
+        Self: Sync,
+    {
+        crate::histogram::count_histogram_par(self, max_bin, threads)
+    }
+
+    /// Compares the counts of this CQF, `a`, with those of `other`, `b`, in one pass and
+    /// without building a CQF. Both CQFs must use the same hasher and number of hash bits.
+    fn similarity<T: CountingQuotientFilter>(&self, other: &T) -> Result<Similarity, CqfError> {
+        crate::similarity::similarity(self, other)
+    }
+
+    /// Returns a new in-memory CQF holding the entries with a count in `min_count..=max_count`.
+    fn prune(&self, min_count: u64, max_count: u64) -> Result<Self, CqfError>
+    where
+        Self::Hasher: Clone,
+    {
+        self.prune_by(min_count, max_count, |_, _| true, None)
+    }
+
+    /// Returns a new CQF holding the entries with a count in `min_count..=max_count`
+    /// for which `predicate(hash, count)` holds.
+    /// The new CQF is sized for the kept entries and is mmapped to `file` if given.
+    fn prune_by<F: FnMut(u64, u64) -> bool>(
+        &self,
+        min_count: u64,
+        max_count: u64,
+        mut predicate: F,
+        file: Option<File>,
+    ) -> Result<Self, CqfError>
+    where
+        Self::Hasher: Clone,
+    {
+        let mut keep = |&(count, hash): &(u64, u64)| {
+            (min_count..=max_count).contains(&count) && predicate(hash, count)
+        };
+
+        let slots_needed: u64 = self
+            .iter()
+            .filter(&mut keep)
+            .map(|(count, _)| self.entry_slots(count))
+            .sum();
+        let hash_bits = self.quotient_bits() + self.remainder_bits();
+        let max_remainder_bits = 8 * std::mem::size_of::<Self::Remainder>() as u64;
+        let quotient_bits = quotient_bits_for_slots(slots_needed)
+            .max(hash_bits.saturating_sub(max_remainder_bits))
+            .min(hash_bits);
+
+        let mut pruned = match file {
+            Some(file) => Self::new_file(
+                quotient_bits,
+                hash_bits,
+                self.invertable(),
+                self.hasher().clone(),
+                file,
+            )?,
+            None => Self::new(
+                quotient_bits,
+                hash_bits,
+                self.invertable(),
+                self.hasher().clone(),
+            )?,
+        };
+        pruned.set_overflow_policy(self.overflow_policy());
+        CqfMerge::insert_sorted(self.iter().filter(&mut keep), &mut pruned)?;
+        Ok(pruned)
+    }
+
+    /// Inserts an item-count pair into the CQF and feeds the resulting count to `tracker`.
+    /// Returns the new count of item on successful insert, or a CqfError.
+    fn insert_tracked<Item: Hash>(
+        &mut self,
+        item: Item,
+        count: u64,
+        tracker: &mut TopK,
+    ) -> Result<u64, CqfError> {
+        let hash = self.calc_hash(item);
+        let new_count = self.insert_by_hash(hash, count)?;
+        tracker.observe(hash, new_count);
+        Ok(new_count)
+    }
+
+    /// Inserts the canonical k-mers of the DNA sequence `seq` with a count of 1 each, skipping
+    /// the windows that hold a non-base such as `N` (see [`kmer::Kmers`](crate::kmer::Kmers)).
+    /// Repeated k-mers are counted together, so each distinct k-mer is inserted once.
+    /// Returns the number of k-mers inserted, or [`CqfError::InvalidArguments`] if `k` is not
+    /// in `1..=32`.
+    fn insert_sequence(&mut self, seq: &[u8], k: usize) -> Result<u64, CqfError> {
+        if !(1..=kmer::MAX_K).contains(&k) {
+            return Err(CqfError::InvalidArguments);
+        }
+        let mut hashes: Vec<u64> = kmer::Kmers::new(seq, k)
+            .flatten()
+            .map(|kmer| self.calc_hash(kmer))
+            .collect();
+        hashes.sort_unstable();
+        for run in hashes.chunk_by(|a, b| a == b) {
+            self.insert_by_hash(run[0], run.len() as u64)?;
+        }
+        Ok(hashes.len() as u64)
+    }
+
+    /// Queries the canonical k-mers of the DNA sequence `seq` in one batch, as counted by
+    /// [`insert_sequence`](Self::insert_sequence), and returns their counts by position.
+    /// Returns [`CqfError::InvalidArguments`] if `k` is not in `1..=32`.
+    fn kmer_coverage(&self, seq: &[u8], k: usize) -> Result<KmerCoverage, CqfError> {
+        if !(1..=kmer::MAX_K).contains(&k) {
+            return Err(CqfError::InvalidArguments);
+        }
+        let kmers: Vec<Option<u64>> = kmer::Kmers::new(seq, k).collect();
+        let hashes: Vec<u64> = kmers
+            .iter()
+            .flatten()
+            .map(|&kmer| self.calc_hash(kmer))
+            .collect();
+        let mut counts = self.query_batch_by_hash(&hashes).into_iter();
+        Ok(KmerCoverage {
+            counts: kmers
+                .iter()
+                .map(|kmer| kmer.and_then(|_| counts.next()))
+                .collect(),
+        })
+    }
+
+    /// Returns the 8 neighbors of `kmer` in the de Bruijn graph of the CQF as
+    /// (base, direction, count): the 4 successors then the 4 predecessors, each in `ACGT` order.
+    /// `kmer` need not be canonical, and directions are relative to its orientation.
+    /// The 8 lookups are batched, so nearby quotients are probed together.
+    /// Returns [`CqfError::InvalidArguments`] if `k` is not in `1..=32`.
+    fn neighbors(&self, kmer: u64, k: usize) -> Result<[(u8, Direction, u64); 8], CqfError> {
+        if !(1..=kmer::MAX_K).contains(&k) {
+            return Err(CqfError::InvalidArguments);
+        }
+        Ok(kmer::neighbors(self, kmer, k))
+    }
+
+    /// Returns an iterator over the unitig starting at `kmer`, walking forward in the
+    /// de Bruijn graph of the CQF (see [`UnitigWalk`]).
+    /// Returns [`CqfError::InvalidArguments`] if `k` is not in `1..=32`.
+    fn walk_unitig(&self, kmer: u64, k: usize) -> Result<UnitigWalk<'_, Self>, CqfError> {
+        if !(1..=kmer::MAX_K).contains(&k) {
+            return Err(CqfError::InvalidArguments);
+        }
+        Ok(UnitigWalk::new(self, kmer, k))
+    }
 
     fn occupied_slots(&self) -> u64;
 
@@ -181,18 +574,66 @@ pub trait CountingQuotientFilter: IntoIterator + Sized {
 
     fn invertable(&self) -> bool;
 
-    fn insert_by_hash(&mut self, hash: u64, count: u64) -> Result<(), CqfError>;
+    fn insert_by_hash(&mut self, hash: u64, count: u64) -> Result<u64, CqfError>;
 
     fn query_by_hash(&self, hash: u64) -> u64;
 
     fn set_count_by_hash(&mut self, hash: u64, count: u64) -> Result<(), CqfError>;
 
+    /// Replaces the count of every entry with `f(hash, count)` in one pass, shifting slots in
+    /// place. Entries are visited in increasing hash order and entries mapped to 0 are removed.
+    /// Returns [`CqfError::Filled`] if a new count needs more slots than the CQF has free,
+    /// or [`CqfError::CountOverflow`] if a new count does not fit. The rewrite stops at that
+    /// entry: the entries before it hold their new counts, it and the ones after keep their
+    /// old counts, so the last hash passed to `f` tells how far the rewrite got.
+    fn map_counts<F: FnMut(u64, u64) -> u64>(&mut self, f: F) -> Result<(), CqfError>;
+
+    /// Multiplies every count by `factor`, rounding down, in one in-place pass over the CQF.
+    /// Entries whose count reaches 0 are removed and the remaining slots compacted.
+    /// Returns [`CqfError::InvalidArguments`] if `factor` is not in `0.0..=1.0`.
+    fn decay(&mut self, factor: f64) -> Result<(), CqfError> {
+        if !(0.0..=1.0).contains(&factor) {
+            return Err(CqfError::InvalidArguments);
+        }
+        // Smaller counts never take more slots, so the pass cannot stop partway
+        self.map_counts(|_, count| ((count as f64 * factor) as u64).min(count))
+    }
+
     fn max_occupied_slots(&self) -> u64;
 
+    fn overflow_policy(&self) -> OverflowPolicy;
+
+    fn set_overflow_policy(&mut self, policy: OverflowPolicy);
+
+    /// Returns the largest count an entry can hold under the overflow policy.
+    fn max_count(&self) -> u64;
+
+    /// Applies the overflow policy to `count`, where `None` stands for a sum past `u64::MAX`.
+    /// Returns the count to store, or [`CqfError::CountOverflow`].
+    fn fit_count(&self, count: Option<u64>) -> Result<u64, CqfError> {
+        let max = self.max_count();
+        match count {
+            Some(count) if count <= max => Ok(count),
+            _ if self.overflow_policy() == OverflowPolicy::Saturate => Ok(max),
+            _ => Err(CqfError::CountOverflow),
+        }
+    }
+
+    /// Adds the counts of an entry found in both inputs of a merge.
+    /// Returns [`CqfError::CountOverflow`] if the sum overflows and the policy does not saturate.
+    fn merge_counts(&self, a_count: u64, b_count: u64) -> Result<u64, CqfError> {
+        self.fit_count(a_count.checked_add(b_count))
+    }
+
+    /// Returns the number of slots an entry with `count` takes, remainder included.
+    fn entry_slots(&self, count: u64) -> u64;
+
     fn quotient_remainder_from_hash(&self, hash: u64) -> (u64, Self::Remainder);
 
     fn calc_hash<Item: Hash>(&self, item: Item) -> u64;
 
+    fn hasher(&self) -> &Self::Hasher;
+
     fn merge_insert(
         &mut self,
         current_quotient: &mut u64,
@@ -200,7 +641,7 @@ pub trait CountingQuotientFilter: IntoIterator + Sized {
         next_quotient: u64,
         new_remainder: u64,
         count: u64,
-    );
+    ) -> Result<(), CqfError>;
 
     fn build_hash(&self, quotient: u64, remainder: u64) -> u64;
 
@@ -208,6 +649,9 @@ pub trait CountingQuotientFilter: IntoIterator + Sized {
 
     /// Returns the slice of bytes representing the CQF.
     fn serialize_to_bytes(&self) -> &[u8];
+
+    /// Scans the CQF and reports its load, clustering and hash distribution.
+    fn stats(&self) -> CqfStats;
 }
 
 // fn set_count_by_hash_cb<F: FnMut(u64) -> u64>(&mut self, hash: u64, count: u64, cb: F) -> Result<u64, CqfError>;
@@ -222,10 +666,26 @@ pub trait CountingQuotientFilter: IntoIterator + Sized {
 
 // }
 
-mod u64_cqf;
-pub use u64_cqf::*;
-mod u32_cqf;
-pub use u32_cqf::*;
+mod generic_cqf;
+pub use generic_cqf::*;
+
+/// CQF with 8-bit remainders.
+pub type U8Cqf<H> = Cqf<U8Blocks, H>;
+/// CQF with 16-bit remainders.
+pub type U16Cqf<H> = Cqf<U16Blocks, H>;
+/// CQF with 32-bit remainders.
+pub type U32Cqf<H> = Cqf<U32Blocks, H>;
+/// CQF with 64-bit remainders.
+pub type U64Cqf<H> = Cqf<U64Blocks, H>;
+/// CQF with 64-bit remainders and the block metadata stored apart from the remainders,
+/// which makes scans that only read the metadata faster.
+pub type U64SoaCqf<H> = Cqf<U64SoaBlocks, H>;
+/// CQF with remainders of exactly `hash_bits - quotient_bits` bits, packed across 64-bit words.
+/// Counter slots are as wide as the remainders, which caps [`CountingQuotientFilter::max_count`]
+/// at `2^remainder_bits - 1` under [`OverflowPolicy::Saturate`] and [`OverflowPolicy::Error`].
+pub type PackedCqf<H> = Cqf<PackedBlocks, H>;
+mod planner;
+pub use planner::*;
 
 pub trait CqfIteratorImpl: Iterator<Item = (u64, u64)> {}
 
@@ -242,6 +702,9 @@ pub trait CqfMergeClosure: Sized {
     );
 }
 
+/// Merges sorted CQF entries into an empty CQF.
+/// Every merge returns [`CqfError::CountOverflow`] if a count does not fit in the new CQF and
+/// its policy does not saturate, in which case the new CQF holds the entries merged so far.
 pub struct CqfMerge();
 
 impl CqfMerge {
@@ -249,7 +712,7 @@ impl CqfMerge {
         mut iter_a: impl CqfIteratorImpl,
         mut iter_b: impl CqfIteratorImpl,
         new_cqf: &mut T,
-    ) {
+    ) -> Result<(), CqfError> {
         let mut current_a = iter_a.next();
         let mut current_b = iter_b.next();
         let mut merged_cqf_current_quotient = 0u64;
@@ -274,7 +737,7 @@ impl CqfMerge {
             let insert_remainder: u64;
             let insert_count: u64;
             if a_quotient == b_quotient && a_remainder == b_remainder {
-                insert_count = a_count + b_count;
+                insert_count = new_cqf.merge_counts(a_count, b_count)?;
                 insert_quotient = a_quotient;
                 insert_remainder = a_remainder;
                 current_a = iter_a.next();
@@ -306,7 +769,7 @@ impl CqfMerge {
                 next_quotient_,
                 insert_remainder,
                 insert_count,
-            );
+            )?;
         }
         while current_a.is_some() {
             let Some(&(a_count, a_hash)) = current_a.as_ref() else {
@@ -326,7 +789,7 @@ impl CqfMerge {
                 next_quotient_,
                 insert_remainder,
                 insert_count,
-            );
+            )?;
         }
         while current_b.is_some() {
             let Some(&(b_count, b_hash)) = current_b.as_ref() else {
@@ -346,8 +809,9 @@ impl CqfMerge {
                 next_quotient_,
                 insert_remainder,
                 insert_count,
-            );
+            )?;
         }
+        Ok(())
     }
 
     pub fn merge_by<T: CountingQuotientFilter>(
@@ -355,7 +819,7 @@ impl CqfMerge {
         mut iter_b: impl CqfIteratorImpl,
         new_cqf: &mut T,
         closure: &mut impl CqfMergeClosure,
-    ) {
+    ) -> Result<(), CqfError> {
         let mut current_a = iter_a.next();
         let mut current_b = iter_b.next();
         let mut merged_cqf_current_quotient = 0u64;
@@ -392,7 +856,7 @@ impl CqfMerge {
                     Some(&mut b_count),
                 );
                 if a_quotient == b_quotient && a_remainder == b_remainder {
-                    insert_count = a_count + b_count;
+                    insert_count = new_cqf.merge_counts(a_count, b_count)?;
                     insert_quotient = a_quotient;
                     insert_remainder = a_remainder;
                     current_a = iter_a.next();
@@ -424,7 +888,7 @@ impl CqfMerge {
                 next_quotient_,
                 insert_remainder,
                 insert_count,
-            );
+            )?;
         }
         while current_a.is_some() {
             let Some(&(a_count, a_hash)) = current_a.as_ref() else {
@@ -454,7 +918,7 @@ impl CqfMerge {
                 next_quotient_,
                 insert_remainder,
                 insert_count,
-            );
+            )?;
         }
         while current_b.is_some() {
             let Some(&(b_count, b_hash)) = current_b.as_ref() else {
@@ -486,8 +950,61 @@ impl CqfMerge {
                 next_quotient_,
                 insert_remainder,
                 insert_count,
-            );
+            )?;
         }
+        Ok(())
+    }
+
+    /// Inserts (count, hash) pairs, sorted by increasing hash, into an empty CQF.
+    pub fn insert_sorted<T: CountingQuotientFilter>(
+        iter: impl Iterator<Item = (u64, u64)>,
+        new_cqf: &mut T,
+    ) -> Result<(), CqfError> {
+        let mut iter = iter.peekable();
+        let mut merged_cqf_current_quotient = 0u64;
+        while let Some((insert_count, hash)) = iter.next() {
+            let (insert_quotient, insert_remainder) = {
+                let v = new_cqf.quotient_remainder_from_hash(hash);
+                (v.0, v.1.into())
+            };
+            let next_quotient_ = Self::next_quotient(new_cqf, iter.peek(), None, insert_quotient);
+            new_cqf.merge_insert(
+                &mut merged_cqf_current_quotient,
+                insert_quotient,
+                next_quotient_,
+                insert_remainder,
+                insert_count,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Merges the entries of several CQFs into `new_cqf`, in one pass over them in hash order
+    /// (see [`MergedCqfIter`]). Entries with the same hash have their counts added.
+    pub fn merge_many<T: CountingQuotientFilter, I: CqfIteratorImpl>(
+        iters: impl IntoIterator<Item = I>,
+        new_cqf: &mut T,
+    ) -> Result<(), CqfError> {
+        let mut merged = MergedCqfIter::new(iters).map(|(_, entry)| entry).peekable();
+        let mut merged_cqf_current_quotient = 0u64;
+        while let Some((mut insert_count, hash)) = merged.next() {
+            while let Some((count, _)) = merged.next_if(|&(_, next)| next == hash) {
+                insert_count = new_cqf.merge_counts(insert_count, count)?;
+            }
+            let (insert_quotient, insert_remainder) = {
+                let v = new_cqf.quotient_remainder_from_hash(hash);
+                (v.0, v.1.into())
+            };
+            let next_quotient_ = Self::next_quotient(new_cqf, merged.peek(), None, insert_quotient);
+            new_cqf.merge_insert(
+                &mut merged_cqf_current_quotient,
+                insert_quotient,
+                next_quotient_,
+                insert_remainder,
+                insert_count,
+            )?;
+        }
+        Ok(())
     }
 
     fn next_quotient(
@@ -566,3 +1083,35 @@ impl<A: CqfIteratorImpl, B: CqfIteratorImpl> Iterator for ZippedCqfIter<A, B> {
         }
     }
 }
+
+/// Iterator over the (count, hash) pairs of several CQF iterators, as (index of the iterator,
+/// (count, hash)), in increasing hash order. Equal hashes come in increasing index order.
+pub struct MergedCqfIter<I: CqfIteratorImpl> {
+    iters: Vec<I>,
+    /// Next pair of each iterator that is not done, as (hash, index, count)
+    heap: BinaryHeap<Reverse<(u64, usize, u64)>>,
+}
+
+impl<I: CqfIteratorImpl> MergedCqfIter<I> {
+    pub fn new(iters: impl IntoIterator<Item = I>) -> Self {
+        let mut iters: Vec<I> = iters.into_iter().collect();
+        let mut heap = BinaryHeap::with_capacity(iters.len());
+        for (i, iter) in iters.iter_mut().enumerate() {
+            if let Some((count, hash)) = iter.next() {
+                heap.push(Reverse((hash, i, count)));
+            }
+        }
+        Self { iters, heap }
+    }
+}
+
+impl<I: CqfIteratorImpl> Iterator for MergedCqfIter<I> {
+    type Item = (usize, (u64, u64));
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse((hash, i, count)) = self.heap.pop()?;
+        if let Some((next_count, next_hash)) = self.iters[i].next() {
+            self.heap.push(Reverse((next_hash, i, next_count)));
+        }
+        Some((i, (count, hash)))
+    }
+}
@@ -0,0 +1,753 @@
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::hash;
+use std::hash::{BuildHasher, Hash};
+use std::os::fd::AsRawFd;
+
+use super::{
+    CountingQuotientFilter, CqfError, CqfIteratorImpl, Metadata, MetadataWrapper, OverflowPolicy,
+    RuntimeData, SLOTS_PER_BLOCK,
+};
+use crate::blocks::{Blocks, SlotValue};
+use crate::stats::CqfStats;
+use crate::utils::{bitselect, ffs, ffsv, saturating_bitmask, SplitMix64};
+
+/// Counting quotient filter stored in the blocks `B`.
+/// Counters take as many slots as their count needs, see [`Blocks::write_counter`].
+pub struct Cqf<B: Blocks, H: BuildHasher> {
+    metadata: MetadataWrapper,
+    blocks: B,
+    runtime_data: RuntimeData<H>,
+}
+
+impl<B: Blocks, H: BuildHasher> CountingQuotientFilter for Cqf<B, H> {
+    type Hasher = H;
+    type Remainder = B::Remainder;
+    type RefIterator<'a>
+        = CqfRefIterator<'a, B, H>
+    where
+        B: 'a,
+        H: 'a;
+    fn new(
+        quotient_bits: u64,
+        hash_bits: u64,
+        invertable: bool,
+        hasher: H,
+    ) -> Result<Self, CqfError> {
+        let (metadata, blocks) =
+            Self::make_metadata_blocks(quotient_bits, hash_bits, invertable, None, true)?;
+        let runtime_data = RuntimeData::new(None, hasher, metadata.num_real_slots);
+        Ok(Self {
+            metadata,
+            blocks,
+            runtime_data,
+        })
+    }
+
+    fn new_file(
+        quotient_bits: u64,
+        hash_bits: u64,
+        invertable: bool,
+        hasher: Self::Hasher,
+        mut file: File,
+    ) -> Result<Self, CqfError> {
+        let (metadata, blocks) = Self::make_metadata_blocks(
+            quotient_bits,
+            hash_bits,
+            invertable,
+            Some(&mut file),
+            true,
+        )?;
+        let runtime_data = RuntimeData::new(Some(file), hasher, metadata.num_real_slots);
+        Ok(Self {
+            metadata,
+            blocks,
+            runtime_data,
+        })
+    }
+
+    fn open_file(hasher: Self::Hasher, mut file: File) -> Result<Self, CqfError> {
+        // Dummy data to reuse function
+        use std::io::Read;
+        let md: Metadata;
+        // read metadata from file
+        unsafe {
+            let mut metadata_buffer = [0u8; std::mem::size_of::<Metadata>()];
+            file.read_exact(&mut metadata_buffer)
+                .map_err(|_| CqfError::FileError)?;
+            let metadata_ptr = metadata_buffer.as_ptr() as *const Metadata;
+            md = *metadata_ptr;
+        }
+        let (metadata, blocks) = Self::make_metadata_blocks(
+            md.quotient_bits,
+            md.quotient_bits + md.remainder_bits,
+            md.invertable(),
+            Some(&mut file),
+            false,
+        )?;
+        let runtime_data = RuntimeData::new(Some(file), hasher, metadata.num_real_slots);
+        Ok(Self {
+            metadata,
+            blocks,
+            runtime_data,
+        })
+    }
+
+    fn calc_hash<Item: Hash>(&self, item: Item) -> u64 {
+        let mut hasher = self.runtime_data.hasher.build_hasher();
+        item.hash(&mut hasher);
+        hash::Hasher::finish(&hasher)
+    }
+
+    fn iter(&self) -> Self::RefIterator<'_> {
+        // self.blocks.advise_seq();
+        if self.metadata.num_occupied_slots == 0 {
+            return CqfRefIterator {
+                cqf: self,
+                current_run_start: 0,
+                current_quotient: 1,
+                end: 0,
+                // num: 0,
+            };
+        }
+        let current_quotient = self.blocks.find_first_occupied_slot();
+        let num_slots = self.metadata.num_real_slots;
+        CqfRefIterator {
+            cqf: self,
+            current_run_start: current_quotient,
+            current_quotient,
+            end: num_slots,
+            // num: 0,
+        }
+    }
+
+    fn iter_from(&self, quotient: u64) -> Self::RefIterator<'_> {
+        let Some(first_quotient) = self.blocks.find_next_occupied_slot(quotient) else {
+            return CqfRefIterator {
+                cqf: self,
+                current_run_start: 0,
+                current_quotient: 1,
+                end: 0,
+            };
+        };
+        CqfRefIterator {
+            cqf: self,
+            current_run_start: first_quotient,
+            current_quotient: std::cmp::max(self.blocks.run_start(first_quotient), first_quotient),
+            end: self.metadata.num_real_slots,
+        }
+    }
+
+    fn hasher(&self) -> &Self::Hasher {
+        &self.runtime_data.hasher
+    }
+
+    fn quotient_bits(&self) -> u64 {
+        self.metadata.quotient_bits
+    }
+
+    fn remainder_bits(&self) -> u64 {
+        self.metadata.remainder_bits
+    }
+    /// Function used internally for merging
+    fn merge_insert(
+        &mut self,
+        current_quotient: &mut u64,
+        new_quotient: u64,
+        next_quotient: u64,
+        new_remainder: u64,
+        count: u64,
+    ) -> Result<(), CqfError> {
+        if count == 0 {
+            return Ok(());
+        }
+        let count = self.fit_count(Some(count))?;
+
+        let remainder = B::Remainder::from_u64(new_remainder);
+        self.blocks.set_occupied(new_quotient, true);
+        if *current_quotient < new_quotient {
+            *current_quotient = new_quotient;
+        }
+        let slots = self
+            .blocks
+            .write_counter(*current_quotient, remainder, count);
+        let end_of_insert = *current_quotient + slots - 1;
+        if next_quotient != new_quotient {
+            self.blocks.set_runend(end_of_insert, true);
+        }
+        self.metadata.num_occupied_slots += slots;
+        *current_quotient += slots;
+
+        let quotient_block_idx = new_quotient / SLOTS_PER_BLOCK as u64;
+        let insert_block_idx = end_of_insert / SLOTS_PER_BLOCK as u64;
+        // Entries are written in order, so this one ends the runs of the earlier quotients
+        for i in (quotient_block_idx + 1)..=insert_block_idx {
+            let offset = end_of_insert + 1 - i * SLOTS_PER_BLOCK as u64;
+            *self.blocks.offset_mut(i * SLOTS_PER_BLOCK as u64) = offset;
+        }
+        Ok(())
+    }
+
+    fn insert_by_hash(&mut self, hash: u64, count: u64) -> Result<u64, CqfError> {
+        if count == 0 {
+            return Ok(self.query_by_hash(hash));
+        } // nothing to do
+        if self.occupied_slots() >= self.max_occupied_slots() {
+            return Err(CqfError::Filled);
+        }
+        let (quotient, remainder) = self.quotient_remainder_from_hash(hash);
+        let mut runstart_index = std::cmp::max(self.blocks.run_start(quotient), quotient);
+        if !self.blocks.is_occupied(quotient) {
+            let count = self.fit_count(Some(count))?;
+            self.write_entry(quotient, runstart_index, 0, remainder, count, true)?;
+            return Ok(count);
+        }
+        loop {
+            let mut qptr = runstart_index;
+            let (current_remainder, current_count) = self.blocks.decode_counter(&mut qptr);
+            let is_last = self.blocks.is_runend(qptr);
+            if current_remainder == remainder {
+                let new_count = self.fit_count(current_count.checked_add(count))?;
+                let used = qptr - runstart_index + 1;
+                self.write_entry(
+                    quotient,
+                    runstart_index,
+                    used,
+                    remainder,
+                    new_count,
+                    is_last,
+                )?;
+                return Ok(new_count);
+            }
+            if current_remainder > remainder {
+                let count = self.fit_count(Some(count))?;
+                self.write_entry(quotient, runstart_index, 0, remainder, count, false)?;
+                return Ok(count);
+            }
+            if is_last {
+                let count = self.fit_count(Some(count))?;
+                self.write_entry(quotient, qptr + 1, 0, remainder, count, true)?;
+                return Ok(count);
+            }
+            runstart_index = qptr + 1;
+        }
+    }
+
+    fn query_by_hash(&self, hash: u64) -> u64 {
+        let (quotient, remainder) = self.quotient_remainder_from_hash(hash);
+        if !self.blocks.is_occupied(quotient) {
+            return 0;
+        }
+        let mut runstart_index = std::cmp::max(self.blocks.run_start(quotient), quotient);
+        loop {
+            let mut qptr = runstart_index;
+            let (current_remainder, current_count) = self.blocks.decode_counter(&mut qptr);
+            if current_remainder == remainder {
+                return current_count;
+            }
+            if self.blocks.is_runend(qptr) {
+                break;
+            }
+            runstart_index = qptr + 1;
+        }
+        0
+    }
+
+    fn set_count_by_hash(&mut self, hash: u64, count: u64) -> Result<(), CqfError> {
+        let (quotient, remainder) = self.quotient_remainder_from_hash(hash);
+        if !self.blocks.is_occupied(quotient) {
+            return Err(CqfError::InvalidArguments);
+        }
+        let mut runstart_index = std::cmp::max(self.blocks.run_start(quotient), quotient);
+        loop {
+            let mut qptr = runstart_index;
+            let (current_remainder, _) = self.blocks.decode_counter(&mut qptr);
+            let is_last = self.blocks.is_runend(qptr);
+            let used = qptr - runstart_index + 1;
+            if current_remainder == remainder {
+                if count == 0 {
+                    self.blocks.remove_slots(quotient, runstart_index, used);
+                    self.metadata.num_occupied_slots -= used;
+                    return Ok(());
+                }
+                let count = self.fit_count(Some(count))?;
+                return self.write_entry(quotient, runstart_index, used, remainder, count, is_last);
+            }
+            if current_remainder > remainder || is_last {
+                // error since we didn't find the remainder
+                return Err(CqfError::InvalidArguments);
+            }
+            runstart_index = qptr + 1;
+        }
+    }
+
+    fn map_counts<F: FnMut(u64, u64) -> u64>(&mut self, mut f: F) -> Result<(), CqfError> {
+        let mut next_quotient = self.blocks.find_next_occupied_slot(0);
+        while let Some(quotient) = next_quotient {
+            let mut index = std::cmp::max(self.blocks.run_start(quotient), quotient);
+            loop {
+                let mut end = index;
+                let (remainder, count) = self.blocks.decode_counter(&mut end);
+                let is_last = self.blocks.is_runend(end);
+                let hash = self.build_hash(quotient, remainder.into());
+                let new_count = self.fit_count(Some(f(hash, count)))?;
+                let used = end - index + 1;
+                if new_count == 0 {
+                    self.blocks.remove_slots(quotient, index, used);
+                    self.metadata.num_occupied_slots -= used;
+                } else {
+                    if new_count != count {
+                        self.write_entry(quotient, index, used, remainder, new_count, is_last)?;
+                    }
+                    index += 1 + self.blocks.counter_slots(new_count);
+                }
+                if is_last {
+                    break;
+                }
+            }
+            next_quotient = self.blocks.find_next_occupied_slot(quotient + 1);
+        }
+        Ok(())
+    }
+
+    fn sample(&self, n: usize, seed: u64) -> Vec<(u64, u64)> {
+        // Counts before each block, from popcounts of the metadata bitmaps: entries are the
+        // slots of a run that are not counter digits
+        let num_blocks = self.blocks.num_blocks();
+        let entries_by_block =
+            |block| self.blocks.used_slots_by_block(block) & !self.blocks.counts_by_block(block);
+        let mut entries_before = Vec::with_capacity(num_blocks + 1);
+        let mut occupieds_before = Vec::with_capacity(num_blocks + 1);
+        let mut runends_before = Vec::with_capacity(num_blocks + 1);
+        let (mut entries, mut occupieds, mut runends) = (0u64, 0u64, 0u64);
+        for block in 0..num_blocks {
+            entries_before.push(entries);
+            occupieds_before.push(occupieds);
+            runends_before.push(runends);
+            entries += entries_by_block(block).count_ones() as u64;
+            occupieds += self.blocks.occupieds_by_block(block).count_ones() as u64;
+            runends += self.blocks.runends_by_block(block).count_ones() as u64;
+        }
+        entries_before.push(entries);
+        occupieds_before.push(occupieds);
+        let total = entries;
+        if n as u64 >= total {
+            return self.iter().collect();
+        }
+
+        // Floyd's algorithm draws n distinct ranks uniformly
+        let mut rng = SplitMix64(seed);
+        let mut ranks = BTreeSet::new();
+        for j in total - n as u64..total {
+            let rank = rng.below(j + 1);
+            if !ranks.insert(rank) {
+                ranks.insert(j);
+            }
+        }
+
+        // Only the entries at the drawn ranks are decoded
+        let mut sample = Vec::with_capacity(n);
+        let mut ranks = ranks.into_iter().peekable();
+        while let Some(&first_rank) = ranks.peek() {
+            let block = entries_before.partition_point(|&before| before <= first_rank) - 1;
+            let block_entries = entries_by_block(block);
+            let block_runends = self.blocks.runends_by_block(block);
+            while let Some(rank) = ranks.next_if(|&rank| rank < entries_before[block + 1]) {
+                let slot = bitselect(block_entries, rank - entries_before[block]);
+                // The run holding the slot is that of the occupied quotient with as many
+                // occupied quotients before it as there are runends before the slot
+                let run = runends_before[block]
+                    + (block_runends & saturating_bitmask(slot)).count_ones() as u64;
+                let quotient_block = occupieds_before.partition_point(|&before| before <= run) - 1;
+                let quotient = (quotient_block * SLOTS_PER_BLOCK) as u64
+                    + bitselect(
+                        self.blocks.occupieds_by_block(quotient_block),
+                        run - occupieds_before[quotient_block],
+                    );
+                let mut index = (block * SLOTS_PER_BLOCK) as u64 + slot;
+                let (remainder, count) = self.blocks.decode_counter(&mut index);
+                sample.push((count, self.build_hash(quotient, remainder.into())));
+            }
+        }
+        sample
+    }
+
+    fn rank(&self, hash: u64) -> (u64, u64) {
+        let num_slots = (self.blocks.num_blocks() * SLOTS_PER_BLOCK) as u64;
+        let past_last =
+            hash.checked_shr((self.quotient_bits() + self.remainder_bits()) as u32) > Some(0);
+        let (quotient, remainder) = self.quotient_remainder_from_hash(hash);
+        // Entries in the slots before the run of the quotient all have smaller quotients
+        let start = if past_last {
+            num_slots
+        } else {
+            std::cmp::max(self.blocks.run_start(quotient), quotient)
+        };
+
+        let (mut distinct, mut total) = (0, 0);
+        let last_block = (start as usize).div_ceil(SLOTS_PER_BLOCK);
+        for block in 0..last_block {
+            let mut entries =
+                self.blocks.used_slots_by_block(block) & !self.blocks.counts_by_block(block);
+            if block == start as usize / SLOTS_PER_BLOCK {
+                entries &= saturating_bitmask(start % SLOTS_PER_BLOCK as u64);
+            }
+            distinct += entries.count_ones() as u64;
+            total += entries.count_ones() as u64;
+            // Entries followed by a counter digit in the same run hold more than 1
+            let next_counts = match block + 1 < self.blocks.num_blocks() {
+                true => self.blocks.counts_by_block(block + 1) << (SLOTS_PER_BLOCK - 1),
+                false => 0,
+            };
+            let mut counted = entries
+                & !self.blocks.runends_by_block(block)
+                & ((self.blocks.counts_by_block(block) >> 1) | next_counts);
+            while counted != 0 {
+                let mut index = (block * SLOTS_PER_BLOCK) as u64 + counted.trailing_zeros() as u64;
+                counted &= counted - 1;
+                total += self.blocks.decode_counter(&mut index).1 - 1;
+            }
+        }
+
+        if !past_last && self.blocks.is_occupied(quotient) {
+            let mut index = start;
+            loop {
+                let (current_remainder, count) = self.blocks.decode_counter(&mut index);
+                if current_remainder >= remainder {
+                    break;
+                }
+                distinct += 1;
+                total += count;
+                if self.blocks.is_runend(index) {
+                    break;
+                }
+                index += 1;
+            }
+        }
+        (distinct, total)
+    }
+
+    fn occupied_slots(&self) -> u64 {
+        self.metadata.num_occupied_slots
+    }
+
+    fn size_bytes(&self) -> u64 {
+        self.metadata.total_size_bytes
+    }
+
+    fn invertable(&self) -> bool {
+        self.metadata.invertable()
+    }
+
+    fn max_occupied_slots(&self) -> u64 {
+        self.runtime_data.max_occupied_slots
+    }
+
+    fn overflow_policy(&self) -> OverflowPolicy {
+        self.runtime_data.overflow_policy
+    }
+
+    fn set_overflow_policy(&mut self, policy: OverflowPolicy) {
+        self.runtime_data.overflow_policy = policy;
+    }
+
+    fn max_count(&self) -> u64 {
+        match self.runtime_data.overflow_policy {
+            OverflowPolicy::Spill => u64::MAX,
+            OverflowPolicy::Saturate | OverflowPolicy::Error => {
+                saturating_bitmask(self.blocks.slot_bits())
+            }
+        }
+    }
+
+    fn entry_slots(&self, count: u64) -> u64 {
+        1 + self.blocks.counter_slots(count)
+    }
+
+    fn quotient_remainder_from_hash(&self, hash: u64) -> (u64, B::Remainder) {
+        let quotient = (hash >> self.metadata.remainder_bits)
+            & saturating_bitmask(self.metadata.quotient_bits);
+        let remainder = hash & saturating_bitmask(self.metadata.remainder_bits);
+        (quotient, B::Remainder::from_u64(remainder))
+    }
+
+    fn build_hash(&self, quotient: u64, remainder: u64) -> u64 {
+        (quotient << self.metadata.remainder_bits) | remainder
+    }
+
+    fn is_file(&self) -> bool {
+        self.runtime_data.file.is_some()
+    }
+
+    fn serialize_to_bytes(&self) -> &[u8] {
+        let metadata_ptr = self.metadata.0.as_ptr();
+        let metadata_bytes = self.metadata.total_size_bytes;
+        unsafe { std::slice::from_raw_parts(metadata_ptr.cast(), metadata_bytes as usize) }
+    }
+
+    fn stats(&self) -> CqfStats {
+        crate::stats::collect(
+            &self.blocks,
+            self.metadata.quotient_bits,
+            self.metadata.remainder_bits,
+            self.metadata.num_real_slots,
+        )
+    }
+}
+
+impl<B: Blocks, H: BuildHasher> Cqf<B, H> {
+    fn make_metadata_blocks(
+        quotient_bits: u64,
+        hash_bits: u64,
+        invertable: bool,
+        file: Option<&mut File>,
+        new: bool,
+    ) -> Result<(MetadataWrapper, B), CqfError> {
+        if hash_bits < quotient_bits
+            || hash_bits > 64
+            || !B::supports_remainder_bits(hash_bits - quotient_bits)
+        {
+            return Err(CqfError::InvalidArguments);
+        }
+        let mut metadata = Metadata::new(quotient_bits, hash_bits, invertable);
+        let blocks_size = B::bytes_needed(metadata.num_blocks as usize, metadata.remainder_bits);
+        metadata.add_size(blocks_size as u64);
+        let mmap_flags;
+        let fd: i32;
+        let prot_flags = libc::PROT_READ | libc::PROT_WRITE;
+        match file {
+            Some(f) => {
+                fd = f.as_raw_fd();
+                mmap_flags = libc::MAP_SHARED;
+                if new {
+                    f.set_len(metadata.total_size_bytes)
+                        .map_err(|_| CqfError::FileError)?;
+                }
+            }
+            None => {
+                fd = -1;
+                mmap_flags = libc::MAP_PRIVATE | libc::MAP_ANONYMOUS;
+            }
+        };
+
+        let buffer = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                metadata.total_size_bytes as usize,
+                prot_flags,
+                mmap_flags,
+                fd,
+                0,
+            )
+        };
+        if buffer == libc::MAP_FAILED {
+            println!(
+                "MMAP ERROR {}, {:?}",
+                std::io::Error::last_os_error()
+                    .raw_os_error()
+                    .expect("unreachable"),
+                std::io::Error::last_os_error()
+            );
+            return Err(CqfError::MmapError);
+        }
+        let mut metadata_wrapper = MetadataWrapper::from(buffer as *mut Metadata);
+        if new {
+            *metadata_wrapper = metadata;
+        }
+        let blocks_ptr = unsafe { buffer.add(std::mem::size_of::<Metadata>()) };
+        let blocks = unsafe {
+            B::from_raw_parts(
+                blocks_ptr as *mut u8,
+                metadata_wrapper.num_blocks as usize,
+                metadata_wrapper.remainder_bits,
+            )
+        };
+        Ok((metadata_wrapper, blocks))
+    }
+
+    /// Writes the counter of `remainder` at `index` in the run of `quotient`, in place of the
+    /// `old_slots` slots of its previous counter (0 for a new entry), growing or shrinking the
+    /// run as needed. `is_last` tells whether the entry ends the run and `count` must not be 0.
+    fn write_entry(
+        &mut self,
+        quotient: u64,
+        index: u64,
+        old_slots: u64,
+        remainder: B::Remainder,
+        count: u64,
+        is_last: bool,
+    ) -> Result<(), CqfError> {
+        let new_slots = 1 + self.blocks.counter_slots(count);
+        if new_slots <= old_slots {
+            self.blocks.write_counter(index, remainder, count);
+            if new_slots < old_slots {
+                self.blocks
+                    .remove_slots(quotient, index + new_slots, old_slots - new_slots);
+                self.metadata.num_occupied_slots -= old_slots - new_slots;
+            }
+            return Ok(());
+        }
+
+        let grow = new_slots - old_slots;
+        if self.occupied_slots() + grow > self.max_occupied_slots() {
+            return Err(CqfError::Filled);
+        }
+        let appends = old_slots == 0 && is_last && self.blocks.is_occupied(quotient);
+        let last_filled = self
+            .blocks
+            .make_room(index + old_slots, grow)
+            .ok_or(CqfError::Filled)?;
+        self.blocks.write_counter(index, remainder, count);
+        for slot in index..index + new_slots {
+            self.blocks.set_runend(slot, false);
+        }
+        if appends {
+            self.blocks.set_runend(index - 1, false);
+        }
+        if is_last {
+            self.blocks.set_runend(index + new_slots - 1, true);
+        }
+        self.blocks.set_occupied(quotient, true);
+        self.metadata.num_occupied_slots += grow;
+        let first_block = (quotient / SLOTS_PER_BLOCK as u64) as usize + 1;
+        let last_block = (last_filled / SLOTS_PER_BLOCK as u64) as usize;
+        self.blocks.recompute_offsets(first_block, last_block);
+        Ok(())
+    }
+}
+
+pub struct CqfConsumingIterator<B: Blocks, H: BuildHasher> {
+    cqf: Cqf<B, H>,
+    current_run_start: u64,
+    current_quotient: u64,
+    end: u64,
+    _num: u64,
+}
+
+impl<B: Blocks, H: BuildHasher> CqfIteratorImpl for CqfConsumingIterator<B, H> {}
+
+pub struct CqfRefIterator<'a, B: Blocks, H: BuildHasher> {
+    cqf: &'a Cqf<B, H>,
+    current_run_start: u64,
+    current_quotient: u64,
+    end: u64,
+    // num: u64,
+}
+
+impl<'a, B: Blocks, H: BuildHasher> Iterator for CqfRefIterator<'a, B, H> {
+    type Item = (u64, u64);
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.current_quotient >= self.end {
+            // self.cqf.blocks.advise_normal();
+            return None;
+        }
+        let (current_remainder, current_count) =
+            self.cqf.blocks.decode_counter(&mut self.current_quotient);
+        let current_hash = self
+            .cqf
+            .build_hash(self.current_run_start, current_remainder.into());
+        if !self.cqf.blocks.is_runend(self.current_quotient) {
+            self.current_quotient += 1;
+            return Some((current_count, current_hash));
+        }
+        self.current_quotient += 1;
+        let mut block_index = self.current_run_start as usize / SLOTS_PER_BLOCK;
+        let mut next_run_slot = ffsv(
+            self.cqf.blocks.occupieds_by_block(block_index),
+            (self.current_run_start % SLOTS_PER_BLOCK as u64) + 1,
+        )
+        .unwrap_or(64);
+        if next_run_slot == 64 {
+            while next_run_slot == 64 && block_index < self.cqf.blocks.len() - 1 {
+                block_index += 1;
+                next_run_slot = ffs(self.cqf.blocks.occupieds_by_block(block_index)).unwrap_or(64);
+            }
+        }
+        self.current_run_start = block_index as u64 * SLOTS_PER_BLOCK as u64 + next_run_slot;
+        self.current_quotient = std::cmp::max(self.current_quotient, self.current_run_start);
+        Some((current_count, current_hash))
+    }
+}
+
+impl<B: Blocks, H: BuildHasher> Iterator for CqfConsumingIterator<B, H> {
+    type Item = (u64, u64);
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.current_quotient >= self.end {
+            // self.cqf.blocks.advise_normal();
+            return None;
+        }
+        let (current_remainder, current_count) =
+            self.cqf.blocks.decode_counter(&mut self.current_quotient);
+        let current_hash = self
+            .cqf
+            .build_hash(self.current_run_start, current_remainder.into());
+        if !self.cqf.blocks.is_runend(self.current_quotient) {
+            self.current_quotient += 1;
+            return Some((current_count, current_hash));
+        }
+        self.current_quotient += 1;
+        let mut block_index = self.current_run_start as usize / SLOTS_PER_BLOCK;
+        // if rank == 64, need to go to next block
+        let mut next_run_slot = ffsv(
+            self.cqf.blocks.occupieds_by_block(block_index),
+            (self.current_run_start % SLOTS_PER_BLOCK as u64) + 1,
+        )
+        .unwrap_or(64);
+        if next_run_slot == 64 {
+            while next_run_slot == 64 && block_index < self.cqf.blocks.len() - 1 {
+                block_index += 1;
+                next_run_slot = ffs(self.cqf.blocks.occupieds_by_block(block_index)).unwrap_or(64);
+            }
+        }
+        self.current_run_start = block_index as u64 * SLOTS_PER_BLOCK as u64 + next_run_slot;
+        if self.current_quotient < self.current_run_start {
+            self.current_quotient = self.current_run_start;
+        }
+        Some((current_count, current_hash))
+    }
+}
+
+impl<B: Blocks, H: BuildHasher> IntoIterator for Cqf<B, H> {
+    type Item = (u64, u64);
+    type IntoIter = CqfConsumingIterator<B, H>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        // self.blocks.advise_seq();
+        if self.metadata.num_occupied_slots == 0 {
+            return Self::IntoIter {
+                cqf: self,
+                current_run_start: 0,
+                current_quotient: 1,
+                end: 0,
+                _num: 0,
+            };
+        }
+        let current_quotient = self.blocks.find_first_occupied_slot();
+        let num_slots = self.metadata.num_real_slots;
+        Self::IntoIter {
+            cqf: self,
+            current_run_start: current_quotient,
+            current_quotient,
+            end: num_slots,
+            _num: 0,
+        }
+    }
+}
+
+impl<B: Blocks, H: BuildHasher> CqfIteratorImpl for CqfRefIterator<'_, B, H> {}
+
+impl<B: Blocks, H: BuildHasher> Drop for Cqf<B, H> {
+    fn drop(&mut self) {
+        // println!("Dropping Cqf");
+        let metadata_ptr = self.metadata.0.as_ptr();
+        let bytes = self.metadata.total_size_bytes;
+        let error = unsafe { libc::munmap(metadata_ptr.cast(), bytes as usize) };
+        if error != 0 {
+            println!(
+                "Error unmapping metadata: {} {:?}",
+                error,
+                std::io::Error::last_os_error()
+            );
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use super::{max_occupied_slots, num_real_slots, CqfError, Metadata};
+use crate::blocks::packed_blocks::PackedBlocks;
+use crate::blocks::slot_blocks::{U16Blocks, U32Blocks, U8Blocks};
+use crate::blocks::u64_blocks::U64Blocks;
+use crate::blocks::u64_soa_blocks::U64SoaBlocks;
+use crate::blocks::Blocks;
+
+/// Returns the smallest `quotient_bits` whose CQF holds `slots` slots below [`MAX_LOAD_FACTOR`](crate::MAX_LOAD_FACTOR).
+pub fn quotient_bits_for_slots(slots: u64) -> u64 {
+    let mut quotient_bits = 1;
+    while quotient_bits < 64 && max_occupied_slots(num_real_slots(quotient_bits)) < slots {
+        quotient_bits += 1;
+    }
+    quotient_bits
+}
+
+/// The block layouts a CQF can be built on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CqfBackend {
+    U8,
+    U16,
+    U32,
+    U64,
+    U64Soa,
+    /// Remainders of exactly `hash_bits - quotient_bits` bits, see [`PackedCqf`](crate::PackedCqf).
+    Packed,
+}
+
+impl CqfBackend {
+    pub const ALL: [CqfBackend; 6] = [
+        CqfBackend::U8,
+        CqfBackend::U16,
+        CqfBackend::U32,
+        CqfBackend::U64,
+        CqfBackend::U64Soa,
+        CqfBackend::Packed,
+    ];
+
+    /// Largest remainder the backend can store, in bits.
+    pub fn max_remainder_bits(&self) -> u64 {
+        match self {
+            CqfBackend::U8 => u8::BITS as u64,
+            CqfBackend::U16 => u16::BITS as u64,
+            CqfBackend::U32 => u32::BITS as u64,
+            CqfBackend::U64 | CqfBackend::U64Soa | CqfBackend::Packed => u64::BITS as u64,
+        }
+    }
+
+    /// Width of a slot in bits, which is also the width of one counter digit.
+    /// Packed slots are as wide as the remainders.
+    pub fn slot_bits(&self, remainder_bits: u64) -> u64 {
+        match self {
+            CqfBackend::Packed => remainder_bits,
+            _ => self.max_remainder_bits(),
+        }
+    }
+
+    /// Number of counter slots an entry with `count` takes after its remainder.
+    pub fn counter_slots(&self, count: u64, remainder_bits: u64) -> u64 {
+        if count <= 1 {
+            0
+        } else {
+            (u64::BITS - count.leading_zeros()).div_ceil(self.slot_bits(remainder_bits) as u32)
+                as u64
+        }
+    }
+
+    /// Returns the `total_size_bytes` of a CQF made with these parameters.
+    pub fn total_size_bytes(&self, quotient_bits: u64, hash_bits: u64) -> Result<u64, CqfError> {
+        if hash_bits < quotient_bits
+            || hash_bits > 64
+            || hash_bits - quotient_bits > self.max_remainder_bits()
+            || (*self == CqfBackend::Packed && hash_bits == quotient_bits)
+        {
+            return Err(CqfError::InvalidArguments);
+        }
+        let mut metadata = Metadata::new(quotient_bits, hash_bits, false);
+        let remainder_bits = metadata.remainder_bits;
+        let num_blocks = metadata.num_blocks as usize;
+        metadata.add_size(match self {
+            CqfBackend::U8 => U8Blocks::bytes_needed(num_blocks, remainder_bits),
+            CqfBackend::U16 => U16Blocks::bytes_needed(num_blocks, remainder_bits),
+            CqfBackend::U32 => U32Blocks::bytes_needed(num_blocks, remainder_bits),
+            CqfBackend::U64 => U64Blocks::bytes_needed(num_blocks, remainder_bits),
+            CqfBackend::U64Soa => U64SoaBlocks::bytes_needed(num_blocks, remainder_bits),
+            CqfBackend::Packed => PackedBlocks::bytes_needed(num_blocks, remainder_bits),
+        } as u64);
+        Ok(metadata.total_size_bytes)
+    }
+}
+
+/// Recommended parameters for a CQF, see [`CqfPlanner`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CqfPlan {
+    pub backend: CqfBackend,
+    pub quotient_bits: u64,
+    pub hash_bits: u64,
+    pub remainder_bits: u64,
+    /// Exact size of the CQF, metadata included.
+    pub total_size_bytes: u64,
+    /// Expected fraction of the slots in use once all items are inserted,
+    /// at most [`MAX_LOAD_FACTOR`](crate::MAX_LOAD_FACTOR).
+    pub expected_load: f64,
+    /// Expected false-positive rate once all items are inserted.
+    pub expected_false_positive_rate: f64,
+}
+
+/// Picks `quotient_bits` and `hash_bits` from the expected contents of a CQF.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CqfPlanner {
+    /// Expected number of distinct items.
+    pub expected_distinct: u64,
+    /// Expected fraction of the distinct items seen more than once.
+    /// Each of them takes the counter slots of `max_count` on top of its remainder slot.
+    pub repeated_fraction: f64,
+    /// Largest expected count, 65535 by default.
+    pub max_count: u64,
+    /// Target false-positive rate.
+    pub false_positive_rate: f64,
+    /// Largest acceptable `total_size_bytes`, if any.
+    pub memory_budget: Option<u64>,
+}
+
+impl CqfPlanner {
+    pub fn new(expected_distinct: u64, repeated_fraction: f64, false_positive_rate: f64) -> Self {
+        Self {
+            expected_distinct,
+            repeated_fraction,
+            max_count: u16::MAX as u64,
+            false_positive_rate,
+            memory_budget: None,
+        }
+    }
+
+    pub fn with_max_count(mut self, max_count: u64) -> Self {
+        self.max_count = max_count;
+        self
+    }
+
+    pub fn with_memory_budget(mut self, bytes: u64) -> Self {
+        self.memory_budget = Some(bytes);
+        self
+    }
+
+    /// Returns the smallest CQF on `backend` that holds the expected items below
+    /// [`MAX_LOAD_FACTOR`](crate::MAX_LOAD_FACTOR) and meets the false-positive target.
+    ///
+    /// Fails with [`CqfError::InvalidArguments`] if the target needs more hash bits
+    /// than the backend supports, or [`CqfError::InvalidSize`] if it exceeds the memory budget.
+    pub fn plan(&self, backend: CqfBackend) -> Result<CqfPlan, CqfError> {
+        let valid_rate = self.false_positive_rate > 0.0 && self.false_positive_rate < 1.0;
+        if !valid_rate || !(0.0..=1.0).contains(&self.repeated_fraction) {
+            return Err(CqfError::InvalidArguments);
+        }
+        let distinct = self.expected_distinct.max(1) as f64;
+
+        // Packed counters shrink with the remainders, so the slots needed depend on the
+        // quotient bits
+        let mut quotient_bits = quotient_bits_for_slots(distinct.ceil() as u64);
+        let (remainder_bits, slots_needed) = loop {
+            if quotient_bits >= 64 {
+                return Err(CqfError::InvalidArguments);
+            }
+            let num_slots = (1u64 << quotient_bits) as f64;
+            // A query collides with one of the stored hashes with probability ~ n / 2^hash_bits
+            let remainder_bits = (distinct / (num_slots * self.false_positive_rate))
+                .log2()
+                .ceil()
+                .max(1.0) as u64;
+            let counter_slots = backend.counter_slots(self.max_count, remainder_bits);
+            let slots_needed = distinct * (1.0 + self.repeated_fraction * counter_slots as f64);
+            if quotient_bits_for_slots(slots_needed.ceil() as u64) <= quotient_bits {
+                break (remainder_bits, slots_needed);
+            }
+            quotient_bits += 1;
+        };
+        let hash_bits = quotient_bits + remainder_bits;
+        let total_size_bytes = backend.total_size_bytes(quotient_bits, hash_bits)?;
+        if self
+            .memory_budget
+            .is_some_and(|budget| total_size_bytes > budget)
+        {
+            return Err(CqfError::InvalidSize);
+        }
+
+        Ok(CqfPlan {
+            backend,
+            quotient_bits,
+            hash_bits,
+            remainder_bits,
+            total_size_bytes,
+            expected_load: slots_needed / num_real_slots(quotient_bits) as f64,
+            expected_false_positive_rate: -(-distinct / 2f64.powi(hash_bits as i32)).exp_m1(),
+        })
+    }
+
+    /// Plans a CQF on every backend.
+    pub fn plan_all(&self) -> Vec<Result<CqfPlan, CqfError>> {
+        CqfBackend::ALL
+            .iter()
+            .map(|&backend| self.plan(backend))
+            .collect()
+    }
+
+    /// Returns the plan with the smallest `total_size_bytes`, if any backend fits.
+    pub fn best(&self) -> Option<CqfPlan> {
+        self.plan_all()
+            .into_iter()
+            .filter_map(Result::ok)
+            .min_by_key(|plan| plan.total_size_bytes)
+    }
+}
@@ -0,0 +1,135 @@
+use std::hash::Hash;
+
+use crate::{CountingQuotientFilter, CqfError, OverflowPolicy};
+
+/// Map from keys to values of `value_bits` bits, stored in a CQF.
+///
+/// A value `v` is stored as the count `v + 1`, so it takes the remainder slot of its key
+/// followed by the counter slots of `v + 1`, and a count of 0 still means the key is absent.
+/// Like the counts of a CQF, lookups can return the value of another key with the same hash.
+pub struct CqfMap<C: CountingQuotientFilter> {
+    cqf: C,
+    value_bits: u64,
+}
+
+impl<C: CountingQuotientFilter> CqfMap<C> {
+    /// Makes a map storing its entries in `cqf`, which must be empty.
+    /// Returns [`CqfError::InvalidArguments`] if `value_bits` is not in `1..=63`
+    /// or `cqf` holds entries.
+    pub fn new(mut cqf: C, value_bits: u64) -> Result<Self, CqfError> {
+        if !(1..=63).contains(&value_bits) || cqf.occupied_slots() != 0 {
+            return Err(CqfError::InvalidArguments);
+        }
+        // Values wider than a slot take extra counter slots
+        cqf.set_overflow_policy(OverflowPolicy::Spill);
+        Ok(Self { cqf, value_bits })
+    }
+
+    pub fn value_bits(&self) -> u64 {
+        self.value_bits
+    }
+
+    /// Returns the largest value the map can hold.
+    pub fn max_value(&self) -> u64 {
+        (1 << self.value_bits) - 1
+    }
+
+    /// Returns the value of key, if present.
+    pub fn get<Item: Hash>(&self, key: Item) -> Option<u64> {
+        self.get_by_hash(self.cqf.calc_hash(key))
+    }
+
+    pub fn get_by_hash(&self, hash: u64) -> Option<u64> {
+        self.cqf.query_by_hash(hash).checked_sub(1)
+    }
+
+    pub fn contains_key<Item: Hash>(&self, key: Item) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Sets the value of key, inserting it if it was not present.
+    /// Returns the previous value, or [`CqfError::InvalidArguments`] if value is wider
+    /// than `value_bits`.
+    pub fn insert<Item: Hash>(&mut self, key: Item, value: u64) -> Result<Option<u64>, CqfError> {
+        let hash = self.cqf.calc_hash(key);
+        self.insert_by_hash(hash, value)
+    }
+
+    pub fn insert_by_hash(&mut self, hash: u64, value: u64) -> Result<Option<u64>, CqfError> {
+        if value > self.max_value() {
+            return Err(CqfError::InvalidArguments);
+        }
+        let previous = self.get_by_hash(hash);
+        if previous.is_some() {
+            self.cqf.set_count_by_hash(hash, value + 1)?;
+        } else {
+            self.cqf.insert_by_hash(hash, value + 1)?;
+        }
+        Ok(previous)
+    }
+
+    /// Replaces the value of key with `f(value)`.
+    /// Returns the new value, or `None` if key is not present.
+    pub fn update<Item: Hash, F: FnOnce(u64) -> u64>(
+        &mut self,
+        key: Item,
+        f: F,
+    ) -> Result<Option<u64>, CqfError> {
+        let hash = self.cqf.calc_hash(key);
+        let Some(value) = self.get_by_hash(hash) else {
+            return Ok(None);
+        };
+        let value = f(value);
+        self.insert_by_hash(hash, value)?;
+        Ok(Some(value))
+    }
+
+    /// Removes key from the map, compacting its slots.
+    /// Returns its value, if it was present.
+    pub fn remove<Item: Hash>(&mut self, key: Item) -> Result<Option<u64>, CqfError> {
+        let hash = self.cqf.calc_hash(key);
+        self.remove_by_hash(hash)
+    }
+
+    pub fn remove_by_hash(&mut self, hash: u64) -> Result<Option<u64>, CqfError> {
+        let Some(value) = self.get_by_hash(hash) else {
+            return Ok(None);
+        };
+        self.cqf.set_count_by_hash(hash, 0)?;
+        Ok(Some(value))
+    }
+
+    /// Returns an iterator over the (hash, value) pairs of the map, in increasing hash order.
+    pub fn iter(&self) -> CqfMapIter<C::RefIterator<'_>> {
+        CqfMapIter {
+            iter: self.cqf.iter(),
+        }
+    }
+
+    /// Returns the CQF holding the entries, with values stored as `value + 1`.
+    pub fn cqf(&self) -> &C {
+        &self.cqf
+    }
+
+    /// Returns the CQF holding the entries, for writing them in bulk.
+    pub(crate) fn cqf_mut(&mut self) -> &mut C {
+        &mut self.cqf
+    }
+
+    pub fn into_inner(self) -> C {
+        self.cqf
+    }
+}
+
+/// Iterator over the (hash, value) pairs of a [`CqfMap`].
+pub struct CqfMapIter<I> {
+    iter: I,
+}
+
+impl<I: Iterator<Item = (u64, u64)>> Iterator for CqfMapIter<I> {
+    type Item = (u64, u64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(count, hash)| (hash, count - 1))
+    }
+}
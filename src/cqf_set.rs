@@ -0,0 +1,127 @@
+use std::hash::Hash;
+
+use crate::{CountingQuotientFilter, CqfError, CqfMerge, EitherOrBoth, ZippedCqfIter};
+
+/// Approximate membership set stored in a CQF.
+///
+/// Every entry has a count of 1, so it takes a single remainder slot and never a counter slot.
+/// Like the queries of a CQF, `contains` can return true for an item that was never inserted
+/// when its hash collides with an inserted one.
+pub struct CqfSet<C: CountingQuotientFilter> {
+    cqf: C,
+}
+
+impl<C: CountingQuotientFilter> CqfSet<C> {
+    /// Makes a set storing its entries in `cqf`, which must be empty.
+    /// Returns [`CqfError::InvalidArguments`] if `cqf` holds entries.
+    pub fn new(cqf: C) -> Result<Self, CqfError> {
+        if cqf.occupied_slots() != 0 {
+            return Err(CqfError::InvalidArguments);
+        }
+        Ok(Self { cqf })
+    }
+
+    /// Returns the number of hashes in the set.
+    pub fn len(&self) -> u64 {
+        self.cqf.occupied_slots()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Inserts item into the set.
+    /// Returns whether it was newly inserted, or a CqfError.
+    pub fn insert<Item: Hash>(&mut self, item: Item) -> Result<bool, CqfError> {
+        let hash = self.cqf.calc_hash(item);
+        self.insert_by_hash(hash)
+    }
+
+    pub fn insert_by_hash(&mut self, hash: u64) -> Result<bool, CqfError> {
+        if self.contains_hash(hash) {
+            return Ok(false);
+        }
+        self.cqf.insert_by_hash(hash, 1)?;
+        Ok(true)
+    }
+
+    pub fn contains<Item: Hash>(&self, item: Item) -> bool {
+        self.contains_hash(self.cqf.calc_hash(item))
+    }
+
+    pub fn contains_hash(&self, hash: u64) -> bool {
+        self.cqf.query_by_hash(hash) != 0
+    }
+
+    /// Removes item from the set, compacting its slot.
+    /// Returns whether it was present.
+    pub fn remove<Item: Hash>(&mut self, item: Item) -> Result<bool, CqfError> {
+        let hash = self.cqf.calc_hash(item);
+        if !self.contains_hash(hash) {
+            return Ok(false);
+        }
+        self.cqf.set_count_by_hash(hash, 0)?;
+        Ok(true)
+    }
+
+    /// Returns an iterator over the hashes of the set, in increasing order.
+    pub fn iter(&self) -> impl Iterator<Item = u64> + '_ {
+        self.cqf.iter().map(|(_, hash)| hash)
+    }
+
+    /// Makes the set of the hashes in `a` or `b` in the empty CQF `cqf`.
+    /// All three CQFs must use the same number of hash bits.
+    pub fn union<A: CountingQuotientFilter, B: CountingQuotientFilter>(
+        a: &CqfSet<A>,
+        b: &CqfSet<B>,
+        cqf: C,
+    ) -> Result<Self, CqfError> {
+        Self::from_zipped(a, b, cqf, |_| true)
+    }
+
+    /// Makes the set of the hashes in both `a` and `b` in the empty CQF `cqf`.
+    /// All three CQFs must use the same number of hash bits.
+    pub fn intersection<A: CountingQuotientFilter, B: CountingQuotientFilter>(
+        a: &CqfSet<A>,
+        b: &CqfSet<B>,
+        cqf: C,
+    ) -> Result<Self, CqfError> {
+        Self::from_zipped(a, b, cqf, |entry| matches!(entry, EitherOrBoth::Both(_, _)))
+    }
+
+    fn from_zipped<A: CountingQuotientFilter, B: CountingQuotientFilter>(
+        a: &CqfSet<A>,
+        b: &CqfSet<B>,
+        cqf: C,
+        mut keep: impl FnMut(&EitherOrBoth<(u64, u64)>) -> bool,
+    ) -> Result<Self, CqfError> {
+        let hash_bits = cqf.quotient_bits() + cqf.remainder_bits();
+        if a.cqf.quotient_bits() + a.cqf.remainder_bits() != hash_bits
+            || b.cqf.quotient_bits() + b.cqf.remainder_bits() != hash_bits
+        {
+            return Err(CqfError::InvalidArguments);
+        }
+        let mut set = Self::new(cqf)?;
+        let zipped = || ZippedCqfIter::new(a.cqf.iter(), b.cqf.iter());
+        let needed = zipped().filter(&mut keep).count() as u64;
+        if needed > set.cqf.max_occupied_slots() {
+            return Err(CqfError::Filled);
+        }
+        let hashes = zipped().filter(&mut keep).map(|entry| match entry {
+            EitherOrBoth::Left((_, hash))
+            | EitherOrBoth::Right((_, hash))
+            | EitherOrBoth::Both((_, hash), _) => (1, hash),
+        });
+        CqfMerge::insert_sorted(hashes, &mut set.cqf)?;
+        Ok(set)
+    }
+
+    /// Returns the CQF holding the entries, each with a count of 1.
+    pub fn cqf(&self) -> &C {
+        &self.cqf
+    }
+
+    pub fn into_inner(self) -> C {
+        self.cqf
+    }
+}
@@ -0,0 +1,134 @@
+use crate::CountingQuotientFilter;
+
+/// Abundance spectrum of a CQF: how many distinct entries have each count.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CountHistogram {
+    /// `bins[c]` is the number of distinct entries with count `c`, for `1 <= c <= max_bin`.
+    /// `bins[0]` is always 0.
+    pub bins: Vec<u64>,
+    /// Number of distinct entries with a count above `max_bin`.
+    pub overflow: u64,
+    /// Number of distinct entries.
+    pub distinct: u64,
+    /// Sum of all counts, which can exceed `u64::MAX` with spilled counts.
+    pub total: u128,
+    /// Number of entries with count 1.
+    pub singletons: u64,
+    /// Sum of `count * log2(count)` over all entries, used for the entropy.
+    count_log_count: f64,
+}
+
+impl CountHistogram {
+    /// Largest `max_bin` a histogram keeps bins for.
+    pub const MAX_BIN: u64 = (1 << 24) - 1;
+
+    /// Makes an empty histogram with bins up to `max_bin`, clamped to [`MAX_BIN`](Self::MAX_BIN).
+    pub fn new(max_bin: u64) -> Self {
+        Self {
+            bins: vec![0; max_bin.min(Self::MAX_BIN) as usize + 1],
+            overflow: 0,
+            distinct: 0,
+            total: 0,
+            singletons: 0,
+            count_log_count: 0.0,
+        }
+    }
+
+    /// Builds the histogram of the (count, hash) pairs of `iter`.
+    pub fn from_counts(iter: impl Iterator<Item = (u64, u64)>, max_bin: u64) -> Self {
+        let mut histogram = Self::new(max_bin);
+        for (count, _) in iter {
+            histogram.add(count);
+        }
+        histogram
+    }
+
+    /// Records one distinct entry with count `count`.
+    pub fn add(&mut self, count: u64) {
+        if count == 0 {
+            return;
+        }
+        match usize::try_from(count)
+            .ok()
+            .and_then(|count| self.bins.get_mut(count))
+        {
+            Some(bin) => *bin += 1,
+            None => self.overflow += 1,
+        }
+        self.distinct += 1;
+        self.total += count as u128;
+        if count == 1 {
+            self.singletons += 1;
+        }
+        self.count_log_count += count as f64 * (count as f64).log2();
+    }
+
+    /// Adds the entries of `other` into this histogram.
+    /// Both histograms must have the same `max_bin`.
+    pub fn merge(&mut self, other: &Self) {
+        assert_eq!(self.bins.len(), other.bins.len(), "max_bin mismatch");
+        for (bin, other_bin) in self.bins.iter_mut().zip(other.bins.iter()) {
+            *bin += other_bin;
+        }
+        self.overflow += other.overflow;
+        self.distinct += other.distinct;
+        self.total += other.total;
+        self.singletons += other.singletons;
+        self.count_log_count += other.count_log_count;
+    }
+
+    pub fn max_bin(&self) -> u64 {
+        self.bins.len() as u64 - 1
+    }
+
+    /// Returns the mean count of the distinct entries, or 0 if there are none.
+    pub fn mean(&self) -> f64 {
+        if self.distinct == 0 {
+            0.0
+        } else {
+            self.total as f64 / self.distinct as f64
+        }
+    }
+
+    /// Returns the Shannon entropy, in bits, of the distribution of counts over the entries.
+    pub fn entropy(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        let total = self.total as f64;
+        (total.log2() - self.count_log_count / total).max(0.0)
+    }
+}
+
+/// Builds the histogram of `cqf` using `threads` threads, each walking a range of quotients.
+pub(crate) fn count_histogram_par<T: CountingQuotientFilter + Sync>(
+    cqf: &T,
+    max_bin: u64,
+    threads: usize,
+) -> CountHistogram {
+    let num_quotients = 1u64 << cqf.quotient_bits();
+    let threads = (threads as u64).clamp(1, num_quotients);
+    let chunk = num_quotients.div_ceil(threads);
+    let remainder_bits = cqf.remainder_bits();
+
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads)
+            .map(|i| {
+                let start = i * chunk;
+                let end = std::cmp::min(start + chunk, num_quotients);
+                scope.spawn(move || {
+                    let iter = cqf
+                        .iter_from(start)
+                        .take_while(|&(_, hash)| hash.unbounded_shr(remainder_bits as u32) < end);
+                    CountHistogram::from_counts(iter, max_bin)
+                })
+            })
+            .collect();
+
+        let mut histogram = CountHistogram::new(max_bin);
+        for handle in handles {
+            histogram.merge(&handle.join().expect("histogram thread panicked"));
+        }
+        histogram
+    })
+}
@@ -0,0 +1,36 @@
+use std::hash::{BuildHasher, Hasher};
+
+use crate::reversible_hasher::write_le_bytes;
+use crate::utils::saturating_bitmask;
+
+/// Hashes integer keys to themselves, keeping their lowest HASH_BITS bits (max 64 bits).
+/// Keys below `2^HASH_BITS` keep their order, so hash ranges are key ranges.
+#[derive(Clone, Copy, Default)]
+pub struct IdentityHasher<const HASH_BITS: u64> {
+    hash: u64,
+}
+
+impl<const HASH_BITS: u64> IdentityHasher<HASH_BITS> {
+    const HASH_MASK: u64 = saturating_bitmask(HASH_BITS);
+}
+
+impl<const HASH_BITS: u64> Hasher for IdentityHasher<HASH_BITS> {
+    fn finish(&self) -> u64 {
+        self.hash & Self::HASH_MASK
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        write_le_bytes(&mut self.hash, bytes);
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct BuildIdentityHasher<const HASH_BITS: u64>;
+
+impl<const HASH_BITS: u64> BuildHasher for BuildIdentityHasher<HASH_BITS> {
+    type Hasher = IdentityHasher<HASH_BITS>;
+
+    fn build_hasher(&self) -> Self::Hasher {
+        IdentityHasher::default()
+    }
+}
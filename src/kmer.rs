@@ -0,0 +1,428 @@
+//! 2-bit encoding of DNA k-mers into u64, for k up to [`MAX_K`].
+//!
+//! Bases are encoded as `A = 0`, `C = 1`, `G = 2`, `T = 3` (upper or lower case), with the first
+//! base of a k-mer in the highest bits, so encoded k-mers sort like their strings. The complement
+//! of a base `b` is `3 - b`. Any other character, such as `N`, is not a base.
+//!
+//! A k-mer takes `2k` bits, so a CQF with [`BuildReversibleHasher<2k>`](crate::BuildReversibleHasher)
+//! and `2k` hash bits stores k-mers without collisions, and counted k-mers can be decoded back
+//! with [`ReversibleHasher::invert_hash`](crate::ReversibleHasher::invert_hash) and [`decode`].
+
+use std::collections::{HashSet, VecDeque};
+
+use crate::CountingQuotientFilter;
+
+/// Largest k whose k-mers fit in a u64.
+pub const MAX_K: usize = 32;
+
+/// Returns the 2-bit code of `base`, or None if it is not one of `ACGTacgt`.
+#[inline]
+pub const fn encode_base(base: u8) -> Option<u64> {
+    match base {
+        b'A' | b'a' => Some(0),
+        b'C' | b'c' => Some(1),
+        b'G' | b'g' => Some(2),
+        b'T' | b't' => Some(3),
+        _ => None,
+    }
+}
+
+/// Returns the upper case base of the 2-bit `code`.
+#[inline]
+pub const fn decode_base(code: u64) -> u8 {
+    b"ACGT"[(code & 3) as usize]
+}
+
+/// Returns the mask of the `2k` bits of a k-mer.
+#[inline]
+pub const fn kmer_mask(k: usize) -> u64 {
+    u64::MAX >> (64 - 2 * k as u32)
+}
+
+/// Encodes `seq` as a k-mer with `k = seq.len()`.
+/// Returns None if `seq` is empty, longer than [`MAX_K`], or holds a character that is not a base.
+pub fn encode(seq: &[u8]) -> Option<u64> {
+    if seq.is_empty() || seq.len() > MAX_K {
+        return None;
+    }
+    seq.iter()
+        .try_fold(0, |kmer, &base| Some(kmer << 2 | encode_base(base)?))
+}
+
+/// Decodes the `k` bases of `kmer`, in upper case.
+pub fn decode(kmer: u64, k: usize) -> Vec<u8> {
+    (0..k).rev().map(|i| decode_base(kmer >> (2 * i))).collect()
+}
+
+/// Returns the reverse complement of the k-mer `kmer`.
+#[inline]
+pub const fn reverse_complement(kmer: u64, k: usize) -> u64 {
+    // Complementing is 3 - b, that is flipping both bits of every base
+    let mut rc = !kmer;
+    // Reverse the order of the 2-bit bases
+    rc = (rc >> 2 & 0x3333_3333_3333_3333) | (rc & 0x3333_3333_3333_3333) << 2;
+    rc = (rc >> 4 & 0x0F0F_0F0F_0F0F_0F0F) | (rc & 0x0F0F_0F0F_0F0F_0F0F) << 4;
+    rc = rc.swap_bytes();
+    rc >> (64 - 2 * k as u32)
+}
+
+/// Returns the canonical form of `kmer`: the smaller of it and its reverse complement.
+#[inline]
+pub const fn canonical(kmer: u64, k: usize) -> u64 {
+    let rc = reverse_complement(kmer, k);
+    if rc < kmer {
+        rc
+    } else {
+        kmer
+    }
+}
+
+/// Iterator over the windows of k bases of a sequence, from a rolling encoding.
+///
+/// Yields one item per window, `seq.len() - k + 1` in all: the canonical k-mer of the window,
+/// or None if the window holds a character that is not a base, such as `N`.
+/// Each step only encodes the new base, and a non-base restarts the window after it.
+#[derive(Debug, Clone)]
+pub struct Kmers<'a> {
+    seq: &'a [u8],
+    k: usize,
+    mask: u64,
+    /// Position of the next base to encode
+    pos: usize,
+    forward: u64,
+    reverse: u64,
+    /// Number of bases encoded since the last non-base, up to k
+    valid: usize,
+}
+
+impl<'a> Kmers<'a> {
+    /// Returns the iterator over the k-mers of `seq`.
+    ///
+    /// # Panics
+    /// If `k` is not in `1..=MAX_K`.
+    pub fn new(seq: &'a [u8], k: usize) -> Self {
+        assert!((1..=MAX_K).contains(&k), "k must be in 1..={}", MAX_K);
+        let mut kmers = Kmers {
+            seq,
+            k,
+            mask: kmer_mask(k),
+            pos: 0,
+            forward: 0,
+            reverse: 0,
+            valid: 0,
+        };
+        // Fill the first window but its last base
+        while kmers.pos + 1 < k && kmers.pos < seq.len() {
+            kmers.push(seq[kmers.pos]);
+        }
+        kmers
+    }
+
+    /// Returns the k-mer of the last window in the order of the sequence, which is not always
+    /// canonical, or None if that window held a non-base or no window was yielded yet.
+    pub fn forward(&self) -> Option<u64> {
+        (self.valid == self.k).then_some(self.forward)
+    }
+
+    #[inline]
+    fn push(&mut self, base: u8) {
+        self.pos += 1;
+        match encode_base(base) {
+            Some(code) => {
+                self.forward = (self.forward << 2 | code) & self.mask;
+                self.reverse = self.reverse >> 2 | (3 - code) << (2 * (self.k - 1));
+                self.valid = (self.valid + 1).min(self.k);
+            }
+            None => self.valid = 0,
+        }
+    }
+}
+
+impl Iterator for Kmers<'_> {
+    type Item = Option<u64>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let &base = self.seq.get(self.pos)?;
+        self.push(base);
+        Some((self.valid == self.k).then_some(self.forward.min(self.reverse)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.seq.len() - self.pos;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for Kmers<'_> {}
+
+/// Counts of the k-mers of a sequence, by position, as returned by
+/// [`CountingQuotientFilter::kmer_coverage`].
+///
+/// Windows holding a non-base have no k-mer, and are left out of the summaries.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct KmerCoverage {
+    /// `counts[i]` is the count of the k-mer starting at position `i`, or None if the window
+    /// holds a non-base.
+    pub counts: Vec<Option<u64>>,
+}
+
+impl KmerCoverage {
+    /// Returns the number of k-mers, not counting the windows that hold a non-base.
+    pub fn num_kmers(&self) -> usize {
+        self.counts.iter().flatten().count()
+    }
+
+    /// Returns the smallest count, or None if there are no k-mers.
+    pub fn min(&self) -> Option<u64> {
+        self.counts.iter().flatten().copied().min()
+    }
+
+    /// Returns the median count, the lower one for an even number of k-mers,
+    /// or None if there are no k-mers.
+    pub fn median(&self) -> Option<u64> {
+        let mut counts: Vec<u64> = self.counts.iter().flatten().copied().collect();
+        if counts.is_empty() {
+            return None;
+        }
+        let mid = (counts.len() - 1) / 2;
+        Some(*counts.select_nth_unstable(mid).1)
+    }
+
+    /// Returns the fraction of the k-mers that are solid, with a count of at least `min_count`,
+    /// or 0 if there are no k-mers.
+    pub fn solid_fraction(&self, min_count: u64) -> f64 {
+        match self.num_kmers() {
+            0 => 0.0,
+            num_kmers => {
+                let solid = self
+                    .counts
+                    .iter()
+                    .flatten()
+                    .filter(|&&c| c >= min_count)
+                    .count();
+                solid as f64 / num_kmers as f64
+            }
+        }
+    }
+}
+
+/// Side of a k-mer that a neighbor in the de Bruijn graph extends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// The neighbor drops the first base of the k-mer and appends a base: a successor.
+    Forward,
+    /// The neighbor drops the last base of the k-mer and prepends a base: a predecessor.
+    Backward,
+}
+
+/// Returns the k-mer that extends `kmer` with `code` on the side of `direction`.
+#[inline]
+pub const fn extend(kmer: u64, k: usize, code: u64, direction: Direction) -> u64 {
+    match direction {
+        Direction::Forward => (kmer << 2 | code) & kmer_mask(k),
+        Direction::Backward => kmer >> 2 | code << (2 * (k - 1)),
+    }
+}
+
+/// Returns the 8 neighbors of `kmer` in the de Bruijn graph of `cqf` as (base, direction, count),
+/// the 4 successors then the 4 predecessors, each in `ACGT` order.
+/// The neighbors are queried by their canonical form in one batch.
+pub(crate) fn neighbors<C: CountingQuotientFilter>(
+    cqf: &C,
+    kmer: u64,
+    k: usize,
+) -> [(u8, Direction, u64); 8] {
+    let edges: [(u64, Direction); 8] = std::array::from_fn(|i| match i {
+        0..4 => (i as u64, Direction::Forward),
+        _ => (i as u64 - 4, Direction::Backward),
+    });
+    let hashes = edges
+        .map(|(code, direction)| cqf.calc_hash(canonical(extend(kmer, k, code, direction), k)));
+    let counts = cqf.query_batch_by_hash(&hashes);
+    std::array::from_fn(|i| (decode_base(edges[i].0), edges[i].1, counts[i]))
+}
+
+/// Iterator over the k-mers of the unitig that starts at a k-mer, walking forward in the
+/// de Bruijn graph of a CQF, as returned by
+/// [`CountingQuotientFilter::walk_unitig`].
+///
+/// Yields (count, k-mer) pairs, with k-mers in the orientation of the walk, starting with the
+/// first k-mer if it is in the CQF. The walk goes on while the last k-mer has a single successor
+/// and that successor has a single predecessor, and stops before a k-mer it already went
+/// through in either orientation. Walking from the reverse complement of a k-mer goes backward.
+pub struct UnitigWalk<'a, C: CountingQuotientFilter> {
+    cqf: &'a C,
+    k: usize,
+    /// Next k-mer to yield, with its count
+    next: Option<(u64, u64)>,
+    /// Canonical forms of the k-mers yielded so far
+    visited: HashSet<u64>,
+}
+
+impl<'a, C: CountingQuotientFilter> UnitigWalk<'a, C> {
+    pub(crate) fn new(cqf: &'a C, kmer: u64, k: usize) -> Self {
+        let count = cqf.query(canonical(kmer, k)).0;
+        UnitigWalk {
+            cqf,
+            k,
+            next: (count != 0).then_some((count, kmer)),
+            visited: HashSet::new(),
+        }
+    }
+
+    /// Returns the single neighbor of `kmer` in the CQF on the side of `direction`, as
+    /// (count, k-mer), or None if there are none or several.
+    fn single_neighbor(&self, kmer: u64, direction: Direction) -> Option<(u64, u64)> {
+        let mut present = neighbors(self.cqf, kmer, self.k)
+            .into_iter()
+            .filter(|&(_, dir, count)| dir == direction && count != 0);
+        let (base, _, count) = present.next()?;
+        if present.next().is_some() {
+            return None;
+        }
+        let code = encode_base(base)?;
+        Some((count, extend(kmer, self.k, code, direction)))
+    }
+}
+
+impl<C: CountingQuotientFilter> Iterator for UnitigWalk<'_, C> {
+    type Item = (u64, u64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (count, kmer) = self.next.take()?;
+        self.visited.insert(canonical(kmer, self.k));
+        self.next = self
+            .single_neighbor(kmer, Direction::Forward)
+            .filter(|&(_, next)| {
+                !self.visited.contains(&canonical(next, self.k))
+                    && self.single_neighbor(next, Direction::Backward).is_some()
+            });
+        Some((count, kmer))
+    }
+}
+
+/// Returns the order of the m-mer `mmer` among minimizers: a mix of its bits, so that
+/// minimizers are spread evenly rather than biased to `A`-rich m-mers.
+#[inline]
+pub const fn minimizer_hash(mmer: u64) -> u64 {
+    // Finalizer of MurmurHash3
+    let mut h = mmer;
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    h ^= h >> 33;
+    h
+}
+
+/// Returns the minimizer of the k-mer `kmer`: its canonical m-mer with the smallest
+/// [`minimizer_hash`]. A k-mer and its reverse complement have the same minimizer.
+///
+/// # Panics
+/// If `m` is greater than `k`.
+pub fn minimizer(kmer: u64, k: usize, m: usize) -> u64 {
+    (0..=k - m)
+        .map(|i| canonical(kmer >> (2 * i) & kmer_mask(m), m))
+        .rev()
+        .min_by_key(|&mmer| minimizer_hash(mmer))
+        .unwrap_or(0)
+}
+
+/// Iterator over the super-k-mers of a sequence: the longest runs of consecutive k-mers that
+/// share a minimizer, with the minimizers of [`minimizer`].
+///
+/// Yields (minimizer, super-k-mer) pairs, the super-k-mer being the part of the sequence that
+/// holds the k-mers of the run. Windows holding a non-base split the sequence, so every k-mer of
+/// [`Kmers`] that is not None is in exactly one super-k-mer.
+#[derive(Debug, Clone)]
+pub struct SuperKmers<'a> {
+    seq: &'a [u8],
+    k: usize,
+    m: usize,
+    /// Canonical m-mer of each window of m bases
+    mmers: Vec<Option<u64>>,
+    /// Next m-mer to add to `candidates`
+    next_mmer: usize,
+    /// Positions of the m-mers after the last non-base that may still become the minimizer
+    /// of a window, as (position, minimizer hash, m-mer), by increasing position and hash
+    candidates: VecDeque<(usize, u64, u64)>,
+    /// Windows starting before this position hold a non-base
+    valid_from: usize,
+    /// Start of the next k-mer window
+    pos: usize,
+    /// Start and minimizer of the super-k-mer being extended
+    current: Option<(usize, u64)>,
+}
+
+impl<'a> SuperKmers<'a> {
+    /// Returns the iterator over the super-k-mers of `seq`.
+    ///
+    /// # Panics
+    /// If `k` is not in `1..=MAX_K` or `m` is not in `1..=k`.
+    pub fn new(seq: &'a [u8], k: usize, m: usize) -> Self {
+        assert!((1..=MAX_K).contains(&k), "k must be in 1..={}", MAX_K);
+        assert!((1..=k).contains(&m), "m must be in 1..={}", k);
+        SuperKmers {
+            seq,
+            k,
+            m,
+            mmers: Kmers::new(seq, m).collect(),
+            next_mmer: 0,
+            candidates: VecDeque::new(),
+            valid_from: 0,
+            pos: 0,
+            current: None,
+        }
+    }
+
+    /// Returns the minimizer of the window starting at `pos`, or None if it holds a non-base.
+    /// Windows must be visited in order.
+    fn window_minimizer(&mut self, pos: usize) -> Option<u64> {
+        let last = pos + self.k - self.m;
+        while self.next_mmer <= last {
+            let i = self.next_mmer;
+            self.next_mmer += 1;
+            match self.mmers[i] {
+                Some(mmer) => {
+                    let hash = minimizer_hash(mmer);
+                    while self.candidates.back().is_some_and(|&(_, h, _)| h > hash) {
+                        self.candidates.pop_back();
+                    }
+                    self.candidates.push_back((i, hash, mmer));
+                }
+                None => {
+                    self.candidates.clear();
+                    self.valid_from = i + 1;
+                }
+            }
+        }
+        while self.candidates.front().is_some_and(|&(i, _, _)| i < pos) {
+            self.candidates.pop_front();
+        }
+        if pos < self.valid_from {
+            return None;
+        }
+        self.candidates.front().map(|&(_, _, mmer)| mmer)
+    }
+}
+
+impl<'a> Iterator for SuperKmers<'a> {
+    type Item = (u64, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pos + self.k <= self.seq.len() {
+            let pos = self.pos;
+            self.pos += 1;
+            let minimizer = self.window_minimizer(pos);
+            if minimizer.is_some() && self.current.map(|(_, mmer)| mmer) == minimizer {
+                continue;
+            }
+            let done = std::mem::replace(&mut self.current, minimizer.map(|mmer| (pos, mmer)));
+            if let Some((start, mmer)) = done {
+                return Some((mmer, &self.seq[start..pos - 1 + self.k]));
+            }
+        }
+        let (start, mmer) = self.current.take()?;
+        Some((mmer, &self.seq[start..self.pos - 1 + self.k]))
+    }
+}
@@ -2,16 +2,40 @@
 #![feature(core_intrinsics)]
 #![warn(clippy::unwrap_used, clippy::unused_result_ok)]
 
-mod blocks;
+pub mod blocks;
+mod colored;
 mod cqf;
+mod cqf_map;
+mod cqf_set;
+mod histogram;
+mod identity_hasher;
+pub mod kmer;
+mod morris;
+mod partition;
 mod reversible_hasher;
+mod similarity;
+mod stats;
+mod top_k;
+mod windowed;
 // mod utils;
 const SLOTS_PER_BLOCK: usize = 64;
 // mod old_cqf;
 // pub use old_cqf::CountingQuotientFilter as OldCqf;
 
+pub use blocks::{Blocks, SlotValue};
+pub use colored::{ColorClasses, ColoredCqf};
 pub use cqf::*;
+pub use cqf_map::{CqfMap, CqfMapIter};
+pub use cqf_set::CqfSet;
+pub use histogram::CountHistogram;
+pub use identity_hasher::{BuildIdentityHasher, IdentityHasher};
+pub use morris::MorrisCqf;
+pub use partition::PartitionedCqf;
 pub use reversible_hasher::*;
+pub use similarity::Similarity;
+pub use stats::CqfStats;
+pub use top_k::TopK;
+pub use windowed::WindowedCqf;
 
 // use std::hash::BuildHasher;
 // use std::ops::{Deref, DerefMut};
@@ -143,4 +167,27 @@ mod utils {
             (1 << nbits) - 1
         }
     }
+
+    /// Small seeded generator, for randomized operations that must be reproducible.
+    pub struct SplitMix64(pub u64);
+
+    impl SplitMix64 {
+        pub fn next_u64(&mut self) -> u64 {
+            self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+            let mut z = self.0;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+            z ^ (z >> 31)
+        }
+
+        /// Returns a float uniformly distributed in `[0, 1)`.
+        pub fn next_f64(&mut self) -> f64 {
+            (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+        }
+
+        /// Returns an integer in `0..n`, with a bias of at most `n / 2^64`.
+        pub fn below(&mut self, n: u64) -> u64 {
+            ((self.next_u64() as u128 * n as u128) >> 64) as u64
+        }
+    }
 }
@@ -0,0 +1,116 @@
+use std::hash::Hash;
+
+use crate::utils::SplitMix64;
+use crate::{CountingQuotientFilter, CqfError, OverflowPolicy};
+
+/// CQF whose counts are Morris counters, for streams where exact counts of the heavy keys
+/// would take several counter slots.
+///
+/// An entry stores its register `c` as its count, so it takes at most one counter slot.
+/// Each occurrence of a key increments its register with probability `(1 + a)^-c`, and
+/// `((1 + a)^c - 1) / a` is an unbiased estimate of the number of occurrences, with a relative
+/// standard error of about `sqrt(a / 2)`. Registers saturate when their counter slot is full,
+/// so narrow slots need a larger error to reach large counts.
+pub struct MorrisCqf<C: CountingQuotientFilter> {
+    cqf: C,
+    a: f64,
+    rng: SplitMix64,
+}
+
+impl<C: CountingQuotientFilter> MorrisCqf<C> {
+    /// Makes a Morris counting filter storing its entries in `cqf`, which must be empty.
+    /// `relative_error` is the target relative standard error of the estimates, and `seed`
+    /// makes the increments reproducible.
+    /// Returns [`CqfError::InvalidArguments`] if `relative_error` is not in `(0, 1)`
+    /// or `cqf` holds entries.
+    pub fn new(mut cqf: C, relative_error: f64, seed: u64) -> Result<Self, CqfError> {
+        if !(relative_error > 0.0 && relative_error < 1.0) || cqf.occupied_slots() != 0 {
+            return Err(CqfError::InvalidArguments);
+        }
+        // Registers must fit in one counter slot
+        cqf.set_overflow_policy(OverflowPolicy::Saturate);
+        Ok(Self {
+            cqf,
+            a: 2.0 * relative_error * relative_error,
+            rng: SplitMix64(seed),
+        })
+    }
+
+    /// Returns the largest register value, at which estimates stop growing.
+    pub fn max_register(&self) -> u64 {
+        self.cqf.max_count()
+    }
+
+    /// Returns the estimate of a saturated register.
+    pub fn max_estimate(&self) -> f64 {
+        self.estimate(self.max_register())
+    }
+
+    /// Adds `count` occurrences of item.
+    /// Returns the new estimate for item, or a CqfError.
+    pub fn insert<Item: Hash>(&mut self, item: Item, count: u64) -> Result<f64, CqfError> {
+        let hash = self.cqf.calc_hash(item);
+        self.insert_by_hash(hash, count)
+    }
+
+    pub fn insert_by_hash(&mut self, hash: u64, count: u64) -> Result<f64, CqfError> {
+        let register = self.cqf.query_by_hash(hash);
+        let increments = self.sample_increments(register, count);
+        let register = self.cqf.insert_by_hash(hash, increments)?;
+        Ok(self.estimate(register))
+    }
+
+    /// Returns the estimated number of occurrences of item.
+    pub fn query<Item: Hash>(&self, item: Item) -> f64 {
+        self.query_by_hash(self.cqf.calc_hash(item))
+    }
+
+    pub fn query_by_hash(&self, hash: u64) -> f64 {
+        self.estimate(self.cqf.query_by_hash(hash))
+    }
+
+    /// Returns an iterator over the (hash, estimate) pairs, in increasing hash order.
+    pub fn iter(&self) -> impl Iterator<Item = (u64, f64)> + '_ {
+        self.cqf
+            .iter()
+            .map(|(register, hash)| (hash, self.estimate(register)))
+    }
+
+    /// Returns the CQF holding the entries, with registers as counts.
+    pub fn cqf(&self) -> &C {
+        &self.cqf
+    }
+
+    pub fn into_inner(self) -> C {
+        self.cqf
+    }
+
+    fn estimate(&self, register: u64) -> f64 {
+        ((1.0 + self.a).powf(register as f64) - 1.0) / self.a
+    }
+
+    /// Returns how much a register at `register` grows over `count` occurrences.
+    /// Instead of drawing once per occurrence, draws the number of occurrences until the next
+    /// increment, which is geometric with the increment probability of the current register.
+    fn sample_increments(&mut self, register: u64, count: u64) -> u64 {
+        let max_register = self.max_register();
+        let mut current = register;
+        let mut remaining = count;
+        while remaining > 0 && current < max_register {
+            let p = (1.0 + self.a).powf(-(current as f64));
+            let trials = if p >= 1.0 {
+                1
+            } else {
+                // Inverse transform of the geometric distribution, u in (0, 1]
+                let u = 1.0 - self.rng.next_f64();
+                (u.ln() / (-p).ln_1p()).floor() as u64 + 1
+            };
+            if trials > remaining {
+                break;
+            }
+            remaining -= trials;
+            current += 1;
+        }
+        current - register
+    }
+}
@@ -0,0 +1,154 @@
+use std::fs::File;
+
+use crate::kmer::{self, SuperKmers};
+use crate::{CountingQuotientFilter, CqfError, CqfMerge};
+
+/// Counts k-mers into `N` bucket CQFs, partitioned by minimizer, for counting out of core.
+///
+/// Sequences are split into super-k-mers (see [`SuperKmers`]) and each super-k-mer is counted
+/// into the bucket of its minimizer, so a canonical k-mer is always counted in the same bucket
+/// and the buckets hold disjoint k-mers. Buckets can be file-backed, and
+/// [`combine`](Self::combine) merges them into one CQF once counting is done.
+pub struct PartitionedCqf<C: CountingQuotientFilter> {
+    buckets: Vec<C>,
+    k: usize,
+    m: usize,
+}
+
+impl<C: CountingQuotientFilter> PartitionedCqf<C> {
+    /// Makes `num_buckets` in-memory buckets for k-mers of `k` bases with minimizers of `m`
+    /// bases. The buckets are made with the same arguments as [`CountingQuotientFilter::new`].
+    /// Returns [`CqfError::InvalidArguments`] if `num_buckets` is 0, `k` is not in `1..=32`
+    /// or `m` is not in `1..=k`.
+    pub fn new(
+        num_buckets: usize,
+        k: usize,
+        m: usize,
+        quotient_bits: u64,
+        hash_bits: u64,
+        invertable: bool,
+        hasher: C::Hasher,
+    ) -> Result<Self, CqfError>
+    where
+        C::Hasher: Clone,
+    {
+        let buckets = (0..num_buckets)
+            .map(|_| C::new(quotient_bits, hash_bits, invertable, hasher.clone()))
+            .collect::<Result<_, _>>()?;
+        Self::from_buckets(buckets, k, m)
+    }
+
+    /// Makes one on-disk bucket per file of `files`, as [`new`](Self::new) does in memory.
+    pub fn new_file(
+        files: Vec<File>,
+        k: usize,
+        m: usize,
+        quotient_bits: u64,
+        hash_bits: u64,
+        invertable: bool,
+        hasher: C::Hasher,
+    ) -> Result<Self, CqfError>
+    where
+        C::Hasher: Clone,
+    {
+        let buckets = files
+            .into_iter()
+            .map(|file| C::new_file(quotient_bits, hash_bits, invertable, hasher.clone(), file))
+            .collect::<Result<_, _>>()?;
+        Self::from_buckets(buckets, k, m)
+    }
+
+    /// Uses `buckets` as the buckets, such as buckets reopened with
+    /// [`CountingQuotientFilter::open_file`]. They must come in the order they were counted in.
+    /// Returns [`CqfError::InvalidArguments`] if there are no buckets, they do not all use the
+    /// same number of hash bits, `k` is not in `1..=32` or `m` is not in `1..=k`.
+    pub fn from_buckets(buckets: Vec<C>, k: usize, m: usize) -> Result<Self, CqfError> {
+        let Some(first) = buckets.first() else {
+            return Err(CqfError::InvalidArguments);
+        };
+        let hash_bits = first.quotient_bits() + first.remainder_bits();
+        if !(1..=kmer::MAX_K).contains(&k)
+            || !(1..=k).contains(&m)
+            || buckets
+                .iter()
+                .any(|bucket| bucket.quotient_bits() + bucket.remainder_bits() != hash_bits)
+        {
+            return Err(CqfError::InvalidArguments);
+        }
+        Ok(Self { buckets, k, m })
+    }
+
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    pub fn m(&self) -> usize {
+        self.m
+    }
+
+    pub fn num_buckets(&self) -> usize {
+        self.buckets.len()
+    }
+
+    pub fn buckets(&self) -> &[C] {
+        &self.buckets
+    }
+
+    pub fn into_buckets(self) -> Vec<C> {
+        self.buckets
+    }
+
+    /// Returns the bucket of the k-mers with minimizer `mmer`.
+    pub fn bucket_of_minimizer(&self, mmer: u64) -> usize {
+        (kmer::minimizer_hash(mmer) % self.buckets.len() as u64) as usize
+    }
+
+    /// Returns the bucket that counts `kmer`, in either orientation.
+    pub fn bucket_of(&self, kmer: u64) -> usize {
+        self.bucket_of_minimizer(kmer::minimizer(kmer, self.k, self.m))
+    }
+
+    /// Counts the canonical k-mers of the DNA sequence `seq`, sending each super-k-mer to the
+    /// bucket of its minimizer. Returns the number of k-mers inserted, or a CqfError.
+    pub fn insert_sequence(&mut self, seq: &[u8]) -> Result<u64, CqfError> {
+        let mut inserted = 0;
+        for (mmer, super_kmer) in SuperKmers::new(seq, self.k, self.m) {
+            let bucket = self.bucket_of_minimizer(mmer);
+            inserted += self.buckets[bucket].insert_sequence(super_kmer, self.k)?;
+        }
+        Ok(inserted)
+    }
+
+    /// Returns the count of the k-mer `kmer`, in either orientation, from its bucket.
+    pub fn query(&self, kmer: u64) -> u64 {
+        let kmer = kmer::canonical(kmer, self.k);
+        self.buckets[self.bucket_of(kmer)].query(kmer).0
+    }
+
+    /// Merges the buckets into `cqf`, which must be empty, in one pass over their entries with
+    /// [`CqfMerge::merge_many`]. Entries of different buckets with the same hash have their
+    /// counts added.
+    /// Returns [`CqfError::InvalidArguments`] if `cqf` does not use the same number of hash
+    /// bits as the buckets or holds entries, or [`CqfError::Filled`] if the entries do not fit.
+    pub fn combine<T: CountingQuotientFilter>(&self, mut cqf: T) -> Result<T, CqfError> {
+        let hash_bits = cqf.quotient_bits() + cqf.remainder_bits();
+        if self.buckets[0].quotient_bits() + self.buckets[0].remainder_bits() != hash_bits
+            || cqf.occupied_slots() != 0
+        {
+            return Err(CqfError::InvalidArguments);
+        }
+        // Buckets are disjoint, so this is exact unless hashes collide across buckets
+        let needed: u64 = self
+            .buckets
+            .iter()
+            .flat_map(|bucket| bucket.iter())
+            .map(|(count, _)| cqf.entry_slots(count))
+            .sum();
+        if needed > cqf.max_occupied_slots() {
+            return Err(CqfError::Filled);
+        }
+
+        CqfMerge::merge_many(self.buckets.iter().map(|bucket| bucket.iter()), &mut cqf)?;
+        Ok(cqf)
+    }
+}
@@ -67,10 +67,16 @@ impl<const HASH_BITS: u64> Hasher for ReversibleHasher<HASH_BITS> {
     }
 
     fn write(&mut self, bytes: &[u8]) {
-        for byte in bytes.iter().rev() {
-            self.hash <<= 8;
-            self.hash |= *byte as u64;
-        }
+        write_le_bytes(&mut self.hash, bytes);
+    }
+}
+
+/// Shifts the little-endian `bytes` into `hash`, so that an integer key written in one call
+/// is the key itself.
+pub(crate) fn write_le_bytes(hash: &mut u64, bytes: &[u8]) {
+    for byte in bytes.iter().rev() {
+        *hash <<= 8;
+        *hash |= *byte as u64;
     }
 }
 
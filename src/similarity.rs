@@ -0,0 +1,111 @@
+use crate::{CountingQuotientFilter, CqfError, EitherOrBoth, ZippedCqfIter};
+
+/// Sums over the entries of two CQFs `a` and `b`, from which the similarity metrics are computed.
+/// Counts are the weights of the entries, and an entry missing from a CQF has a weight of 0.
+/// Count sums are kept in `u128` and products in `f64`, so counts up to `u64::MAX` cannot
+/// overflow them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Similarity {
+    /// Number of distinct entries of `a`.
+    pub distinct_a: u64,
+    /// Number of distinct entries of `b`.
+    pub distinct_b: u64,
+    /// Number of distinct entries in both `a` and `b`.
+    pub shared: u64,
+    /// Sum of the counts of `a`.
+    pub total_a: u128,
+    /// Sum of the counts of `b`.
+    pub total_b: u128,
+    /// Sum of `min(count_a, count_b)` over all entries.
+    pub sum_min: u128,
+    /// Sum of `max(count_a, count_b)` over all entries.
+    pub sum_max: u128,
+    /// Sum of `count_a * count_b` over all entries.
+    pub dot: f64,
+    /// Sum of `count_a^2` over all entries.
+    pub squares_a: f64,
+    /// Sum of `count_b^2` over all entries.
+    pub squares_b: f64,
+}
+
+impl Similarity {
+    /// Records an entry with count `a_count` in `a` and `b_count` in `b`.
+    pub fn add(&mut self, a_count: u64, b_count: u64) {
+        self.distinct_a += (a_count != 0) as u64;
+        self.distinct_b += (b_count != 0) as u64;
+        self.shared += (a_count != 0 && b_count != 0) as u64;
+        self.total_a += a_count as u128;
+        self.total_b += b_count as u128;
+        self.sum_min += a_count.min(b_count) as u128;
+        self.sum_max += a_count.max(b_count) as u128;
+        self.dot += a_count as f64 * b_count as f64;
+        self.squares_a += a_count as f64 * a_count as f64;
+        self.squares_b += b_count as f64 * b_count as f64;
+    }
+
+    /// Returns the number of distinct entries in `a` or `b`.
+    pub fn union(&self) -> u64 {
+        self.distinct_a + self.distinct_b - self.shared
+    }
+
+    /// Returns the Jaccard index of the sets of entries, 1 if both are empty.
+    pub fn jaccard(&self) -> f64 {
+        match self.union() {
+            0 => 1.0,
+            union => self.shared as f64 / union as f64,
+        }
+    }
+
+    /// Returns the weighted Jaccard index, `sum(min) / sum(max)`, 1 if both are empty.
+    pub fn weighted_jaccard(&self) -> f64 {
+        match self.sum_max {
+            0 => 1.0,
+            sum_max => self.sum_min as f64 / sum_max as f64,
+        }
+    }
+
+    /// Returns the fraction of the entries of `a` that are in `b`, 1 if `a` is empty.
+    pub fn containment(&self) -> f64 {
+        match self.distinct_a {
+            0 => 1.0,
+            distinct_a => self.shared as f64 / distinct_a as f64,
+        }
+    }
+
+    /// Returns the cosine similarity of the count vectors, 0 if either is empty.
+    pub fn cosine(&self) -> f64 {
+        if self.squares_a == 0.0 || self.squares_b == 0.0 {
+            return 0.0;
+        }
+        self.dot / (self.squares_a.sqrt() * self.squares_b.sqrt())
+    }
+
+    /// Returns the Bray-Curtis dissimilarity, `1 - 2 * sum(min) / (total_a + total_b)`,
+    /// 0 if both are empty.
+    pub fn bray_curtis(&self) -> f64 {
+        match self.total_a + self.total_b {
+            0 => 0.0,
+            total => 1.0 - 2.0 * self.sum_min as f64 / total as f64,
+        }
+    }
+}
+
+/// Streams over the entries of `a` and `b` in hash order and sums them into a [`Similarity`].
+/// Returns [`CqfError::InvalidArguments`] if the CQFs do not use the same number of hash bits.
+pub(crate) fn similarity<A: CountingQuotientFilter, B: CountingQuotientFilter>(
+    a: &A,
+    b: &B,
+) -> Result<Similarity, CqfError> {
+    if a.quotient_bits() + a.remainder_bits() != b.quotient_bits() + b.remainder_bits() {
+        return Err(CqfError::InvalidArguments);
+    }
+    let mut similarity = Similarity::default();
+    for entry in ZippedCqfIter::new(a.iter(), b.iter()) {
+        match entry {
+            EitherOrBoth::Left((a_count, _)) => similarity.add(a_count, 0),
+            EitherOrBoth::Right((b_count, _)) => similarity.add(0, b_count),
+            EitherOrBoth::Both((a_count, _), (b_count, _)) => similarity.add(a_count, b_count),
+        }
+    }
+    Ok(similarity)
+}
@@ -0,0 +1,186 @@
+use crate::blocks::Blocks;
+
+/// Number of quotient regions used to report skew in the hash distribution.
+const STATS_REGIONS: u64 = 64;
+
+/// Load and clustering statistics of a CQF, gathered by scanning its metadata.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CqfStats {
+    /// Number of nominal slots (2^quotient_bits).
+    pub num_slots: u64,
+    /// Number of slots actually allocated, including the overflow slots past the last quotient.
+    pub num_real_slots: u64,
+    /// Slots in use, holding either a remainder or a counter.
+    pub occupied_slots: u64,
+    /// Slots holding a counter rather than a remainder.
+    pub counter_slots: u64,
+    /// Number of distinct entries, which is the number of slots holding a remainder.
+    pub distinct_entries: u64,
+    /// Number of quotients with at least one entry.
+    pub occupied_quotients: u64,
+    /// `run_lengths[n]` is the number of runs spanning `n` slots.
+    pub run_lengths: Vec<u64>,
+    /// `cluster_lengths[n]` is the number of clusters spanning `n` slots.
+    pub cluster_lengths: Vec<u64>,
+    /// Largest block offset.
+    pub largest_offset: u64,
+    /// Mean block offset.
+    pub average_offset: f64,
+    /// Number of distinct entries in each of the equal-sized quotient regions.
+    pub region_entries: Vec<u64>,
+    /// Number of remainder bits, used for the false-positive estimate.
+    pub remainder_bits: u64,
+}
+
+impl CqfStats {
+    /// Fraction of the nominal slots in use.
+    pub fn load_factor(&self) -> f64 {
+        self.occupied_slots as f64 / self.num_slots as f64
+    }
+
+    pub fn num_runs(&self) -> u64 {
+        self.occupied_quotients
+    }
+
+    pub fn num_clusters(&self) -> u64 {
+        self.cluster_lengths.iter().sum()
+    }
+
+    pub fn average_run_length(&self) -> f64 {
+        average_length(&self.run_lengths)
+    }
+
+    pub fn average_cluster_length(&self) -> f64 {
+        average_length(&self.cluster_lengths)
+    }
+
+    pub fn longest_cluster(&self) -> u64 {
+        self.cluster_lengths.len().saturating_sub(1) as u64
+    }
+
+    /// Estimated probability that a query for an absent item reports a nonzero count,
+    /// which is the chance its hash collides with one of the stored hashes.
+    pub fn false_positive_rate(&self) -> f64 {
+        let hash_space = (self.num_slots as f64) * 2f64.powi(self.remainder_bits as i32);
+        -(-(self.distinct_entries as f64) / hash_space).exp_m1()
+    }
+
+    /// Ratio of the fullest quotient region to the mean region.
+    /// A uniform hash keeps this close to 1.
+    pub fn region_skew(&self) -> f64 {
+        let mean = self.distinct_entries as f64 / self.region_entries.len() as f64;
+        if mean == 0.0 {
+            return 1.0;
+        }
+        self.region_entries.iter().copied().max().unwrap_or(0) as f64 / mean
+    }
+
+    /// Coefficient of variation of the number of entries per quotient region.
+    pub fn region_coefficient_of_variation(&self) -> f64 {
+        let n = self.region_entries.len() as f64;
+        let mean = self.distinct_entries as f64 / n;
+        if mean == 0.0 {
+            return 0.0;
+        }
+        let variance = self
+            .region_entries
+            .iter()
+            .map(|&e| (e as f64 - mean).powi(2))
+            .sum::<f64>()
+            / n;
+        variance.sqrt() / mean
+    }
+}
+
+fn average_length(lengths: &[u64]) -> f64 {
+    let (num, total) = lengths
+        .iter()
+        .enumerate()
+        .fold((0u64, 0u64), |(num, total), (len, &n)| {
+            (num + n, total + len as u64 * n)
+        });
+    if num == 0 {
+        0.0
+    } else {
+        total as f64 / num as f64
+    }
+}
+
+fn record_length(lengths: &mut Vec<u64>, length: u64) {
+    let length = length as usize;
+    if lengths.len() <= length {
+        lengths.resize(length + 1, 0);
+    }
+    lengths[length] += 1;
+}
+
+/// Scans the runs of `blocks` and collects their statistics.
+pub(crate) fn collect<B: Blocks>(
+    blocks: &B,
+    quotient_bits: u64,
+    remainder_bits: u64,
+    num_real_slots: u64,
+) -> CqfStats {
+    let num_slots = 1u64 << quotient_bits;
+    let num_regions = STATS_REGIONS.min(num_slots);
+    let region_size = num_slots / num_regions;
+
+    let mut stats = CqfStats {
+        num_slots,
+        num_real_slots,
+        occupied_slots: 0,
+        counter_slots: 0,
+        distinct_entries: 0,
+        occupied_quotients: 0,
+        run_lengths: Vec::new(),
+        cluster_lengths: Vec::new(),
+        largest_offset: 0,
+        average_offset: 0.0,
+        region_entries: vec![0; num_regions as usize],
+        remainder_bits,
+    };
+
+    let mut offset_sum = 0u64;
+    for block in 0..blocks.num_blocks() {
+        let offset = blocks.offset_by_block(block);
+        offset_sum += offset;
+        stats.largest_offset = stats.largest_offset.max(offset);
+    }
+    stats.average_offset = offset_sum as f64 / blocks.num_blocks() as f64;
+
+    let mut cluster_start = 0u64;
+    // One past the end of the previous run
+    let mut previous_end = 0u64;
+    let mut next_quotient = blocks.find_next_occupied_slot(0);
+    while let Some(quotient) = next_quotient {
+        let run_start = std::cmp::max(quotient, previous_end);
+        let run_end = blocks.find_next_runend(run_start);
+        let run_length = run_end - run_start + 1;
+        let counters = (run_start..=run_end)
+            .filter(|&slot| blocks.is_count(slot))
+            .count() as u64;
+
+        if stats.occupied_quotients == 0 {
+            cluster_start = run_start;
+        } else if run_start > previous_end {
+            record_length(&mut stats.cluster_lengths, previous_end - cluster_start);
+            cluster_start = run_start;
+        }
+
+        stats.occupied_quotients += 1;
+        stats.occupied_slots += run_length;
+        stats.counter_slots += counters;
+        stats.region_entries[(quotient / region_size).min(num_regions - 1) as usize] +=
+            run_length - counters;
+        record_length(&mut stats.run_lengths, run_length);
+
+        previous_end = run_end + 1;
+        next_quotient = blocks.find_next_occupied_slot(quotient + 1);
+    }
+    if stats.occupied_quotients != 0 {
+        record_length(&mut stats.cluster_lengths, previous_end - cluster_start);
+    }
+    stats.distinct_entries = stats.occupied_slots - stats.counter_slots;
+
+    stats
+}
@@ -0,0 +1,104 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, BinaryHeap, HashMap};
+
+use crate::CqfIteratorImpl;
+
+/// Returns the (hash, count) pairs of the `k` entries of `iter` with the largest counts,
+/// ordered from most to least frequent. Ties are broken by increasing hash.
+pub(crate) fn top_k(iter: impl CqfIteratorImpl, k: usize) -> Vec<(u64, u64)> {
+    if k == 0 {
+        return Vec::new();
+    }
+    // Min-heap on count, so the least frequent of the current top k is evicted first
+    let mut heap = BinaryHeap::with_capacity(k + 1);
+    for (count, hash) in iter {
+        if heap.len() < k {
+            heap.push(Reverse((count, Reverse(hash))));
+        } else if let Some(Reverse((min_count, _))) = heap.peek() {
+            if count > *min_count {
+                heap.pop();
+                heap.push(Reverse((count, Reverse(hash))));
+            }
+        }
+    }
+    let mut top: Vec<_> = heap
+        .into_iter()
+        .map(|Reverse((count, Reverse(hash)))| (hash, count))
+        .collect();
+    top.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    top
+}
+
+/// Keeps the `k` most frequent hashes of a stream up to date.
+///
+/// Observed counts are running totals, such as the counts returned by
+/// [`CountingQuotientFilter::insert`](crate::CountingQuotientFilter::insert),
+/// so a new observation of a hash replaces the previous one.
+///
+/// Evicted hashes are not remembered: a hash gets back in when an observation of it beats the
+/// smallest tracked count. The set is exact as long as every change of a count is observed, as
+/// with [`insert_tracked`](crate::CountingQuotientFilter::insert_tracked), and approximate
+/// otherwise, for example when tracked counts go down below those of evicted hashes.
+#[derive(Debug, Clone, Default)]
+pub struct TopK {
+    k: usize,
+    counts: HashMap<u64, u64>,
+    /// (count, hash) pairs of the tracked hashes, ordered by count then decreasing hash
+    ranked: BTreeSet<(u64, Reverse<u64>)>,
+}
+
+impl TopK {
+    pub fn new(k: usize) -> Self {
+        Self {
+            k,
+            counts: HashMap::with_capacity(k),
+            ranked: BTreeSet::new(),
+        }
+    }
+
+    /// Records that `hash` now has count `count`.
+    pub fn observe(&mut self, hash: u64, count: u64) {
+        if self.k == 0 {
+            return;
+        }
+        if let Some(current) = self.counts.get_mut(&hash) {
+            self.ranked.remove(&(*current, Reverse(hash)));
+            *current = count;
+            self.ranked.insert((count, Reverse(hash)));
+            return;
+        }
+        if self.counts.len() == self.k {
+            match self.ranked.first() {
+                Some(&(min_count, Reverse(min_hash))) if count > min_count => {
+                    self.ranked.pop_first();
+                    self.counts.remove(&min_hash);
+                }
+                _ => return,
+            }
+        }
+        self.counts.insert(hash, count);
+        self.ranked.insert((count, Reverse(hash)));
+    }
+
+    /// Returns the smallest count in the tracked set, if any hash is tracked.
+    pub fn min_count(&self) -> Option<u64> {
+        self.ranked.first().map(|&(count, _)| count)
+    }
+
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// Returns the tracked (hash, count) pairs, ordered from most to least frequent.
+    pub fn to_vec(&self) -> Vec<(u64, u64)> {
+        self.ranked
+            .iter()
+            .rev()
+            .map(|&(count, Reverse(hash))| (hash, count))
+            .collect()
+    }
+}
@@ -0,0 +1,138 @@
+use std::collections::VecDeque;
+use std::hash::Hash;
+
+use crate::{quotient_bits_for_slots, CountingQuotientFilter, CqfError, CqfMerge};
+
+/// Counts over a sliding window of the last `N` intervals, kept as one CQF per interval.
+///
+/// Inserts go to the generation of the current interval and queries sum all generations.
+/// [`rotate`](Self::rotate) starts a new interval, dropping the oldest generation once the
+/// window holds `N` of them. Works with any CQF type, such as [`U32Cqf`](crate::U32Cqf)
+/// or [`U64Cqf`](crate::U64Cqf).
+pub struct WindowedCqf<C: CountingQuotientFilter>
+where
+    C::Hasher: Clone,
+{
+    /// Oldest generation first, the current one last
+    generations: VecDeque<C>,
+    num_generations: usize,
+    quotient_bits: u64,
+    hash_bits: u64,
+    invertable: bool,
+    hasher: C::Hasher,
+}
+
+impl<C: CountingQuotientFilter> WindowedCqf<C>
+where
+    C::Hasher: Clone,
+{
+    /// Makes a window of up to `num_generations` in-memory CQFs, starting with one generation.
+    /// The generations are made with the same arguments as [`CountingQuotientFilter::new`].
+    /// Returns [`CqfError::InvalidArguments`] if `num_generations` is 0.
+    pub fn new(
+        num_generations: usize,
+        quotient_bits: u64,
+        hash_bits: u64,
+        invertable: bool,
+        hasher: C::Hasher,
+    ) -> Result<Self, CqfError> {
+        if num_generations == 0 {
+            return Err(CqfError::InvalidArguments);
+        }
+        let mut window = Self {
+            generations: VecDeque::with_capacity(num_generations),
+            num_generations,
+            quotient_bits,
+            hash_bits,
+            invertable,
+            hasher,
+        };
+        window.generations.push_back(window.new_generation()?);
+        Ok(window)
+    }
+
+    fn new_generation(&self) -> Result<C, CqfError> {
+        C::new(
+            self.quotient_bits,
+            self.hash_bits,
+            self.invertable,
+            self.hasher.clone(),
+        )
+    }
+
+    /// Returns the maximum number of generations in the window.
+    pub fn num_generations(&self) -> usize {
+        self.num_generations
+    }
+
+    /// Returns the generations, oldest first.
+    pub fn generations(&self) -> impl Iterator<Item = &C> {
+        self.generations.iter()
+    }
+
+    /// Returns the generation receiving the inserts.
+    pub fn current(&self) -> &C {
+        self.generations.back().expect("window has a generation")
+    }
+
+    fn current_mut(&mut self) -> &mut C {
+        self.generations
+            .back_mut()
+            .expect("window has a generation")
+    }
+
+    /// Starts a new generation, expiring the oldest one if the window is full.
+    /// Returns the expired generation, if any.
+    pub fn rotate(&mut self) -> Result<Option<C>, CqfError> {
+        let generation = self.new_generation()?;
+        let expired = if self.generations.len() == self.num_generations {
+            self.generations.pop_front()
+        } else {
+            None
+        };
+        self.generations.push_back(generation);
+        Ok(expired)
+    }
+
+    /// Inserts an item-count pair into the current generation.
+    /// Returns the count of item over the window on successful insert, or a CqfError.
+    pub fn insert<Item: Hash>(&mut self, item: Item, count: u64) -> Result<u64, CqfError> {
+        let hash = self.current().calc_hash(item);
+        self.insert_by_hash(hash, count)
+    }
+
+    pub fn insert_by_hash(&mut self, hash: u64, count: u64) -> Result<u64, CqfError> {
+        self.current_mut().insert_by_hash(hash, count)?;
+        Ok(self.query_by_hash(hash))
+    }
+
+    /// Returns the (count, hash) of item, summing the counts of all generations.
+    pub fn query<Item: Hash>(&self, item: Item) -> (u64, u64) {
+        let hash = self.current().calc_hash(item);
+        (self.query_by_hash(hash), hash)
+    }
+
+    pub fn query_by_hash(&self, hash: u64) -> u64 {
+        self.generations
+            .iter()
+            .map(|generation| generation.query_by_hash(hash))
+            .fold(0, u64::saturating_add)
+    }
+
+    /// Merges the generations into one in-memory CQF holding the counts over the window.
+    /// The merged CQF grows past `quotient_bits` if the generations do not fit in it.
+    pub fn merged(&self) -> Result<C, CqfError> {
+        let slots: u64 = self.generations.iter().map(|g| g.occupied_slots()).sum();
+        let quotient_bits = quotient_bits_for_slots(slots)
+            .max(self.quotient_bits)
+            .min(self.hash_bits);
+        let mut merged = C::new(
+            quotient_bits,
+            self.hash_bits,
+            self.invertable,
+            self.hasher.clone(),
+        )?;
+        CqfMerge::merge_many(self.generations.iter().map(|g| g.iter()), &mut merged)?;
+        Ok(merged)
+    }
+}
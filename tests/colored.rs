@@ -0,0 +1,204 @@
+use cqfrs::kmer::{self, Kmers};
+use cqfrs::{
+    BuildReversibleHasher, ColorClasses, ColoredCqf, CountingQuotientFilter, CqfError, U32Cqf,
+    U64Cqf,
+};
+use fastrand::Rng;
+use hashbrown::{HashMap, HashSet};
+
+const K: usize = 21;
+const HASH_BITS: u64 = 2 * K as u64;
+const LOGN_SLOTS: u64 = 14;
+
+type Sample = U64Cqf<BuildReversibleHasher<HASH_BITS>>;
+
+fn make_sample(quotient_bits: u64) -> Sample {
+    U64Cqf::new(
+        quotient_bits,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf")
+}
+
+fn random_sequence(rng: &mut Rng, len: usize) -> Vec<u8> {
+    (0..len).map(|_| b"ACGT"[rng.usize(..4)]).collect()
+}
+
+/// Returns the samples holding each canonical k-mer of `sequences`.
+fn reference(sequences: &[Vec<Vec<u8>>]) -> HashMap<u64, Vec<usize>> {
+    let mut expected: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (i, sample) in sequences.iter().enumerate() {
+        let kmers: HashSet<u64> = sample
+            .iter()
+            .flat_map(|seq| Kmers::new(seq, K).flatten())
+            .collect();
+        for kmer in kmers {
+            expected.entry(kmer).or_default().push(i);
+        }
+    }
+    expected
+}
+
+#[test]
+fn colored_index() {
+    let mut rng = Rng::with_seed(7);
+    // Samples share a core genome, and each has accessory genes shared with some others
+    let core = random_sequence(&mut rng, 2_000);
+    let accessory: Vec<Vec<u8>> = (0..6).map(|_| random_sequence(&mut rng, 300)).collect();
+    let sequences: Vec<Vec<Vec<u8>>> = (0..5)
+        .map(|i| {
+            let mut sample = vec![core.clone()];
+            sample.extend(
+                accessory
+                    .iter()
+                    .enumerate()
+                    .filter(|&(j, _)| (i + j) % 3 != 0)
+                    .map(|(_, gene)| gene.clone()),
+            );
+            sample.push(random_sequence(&mut rng, 200));
+            sample
+        })
+        .collect();
+    let samples: Vec<Sample> = sequences
+        .iter()
+        .map(|sample| {
+            let mut cqf = make_sample(LOGN_SLOTS);
+            for seq in sample {
+                cqf.insert_sequence(seq, K).expect("insert failed!");
+            }
+            cqf
+        })
+        .collect();
+
+    let colored = ColoredCqf::build(&samples, K, make_sample(LOGN_SLOTS)).expect("build failed");
+    assert_eq!(colored.num_samples(), 5);
+    let expected = reference(&sequences);
+    for (&kmer, expected_samples) in expected.iter() {
+        assert_eq!(&colored.samples(kmer), expected_samples);
+        // Queries in the other orientation find the canonical k-mer
+        let rc = kmer::reverse_complement(kmer, K);
+        assert_eq!(&colored.samples(rc), expected_samples);
+        assert_eq!(colored.color_class(rc), colored.color_class(kmer));
+    }
+    let distinct_classes: HashSet<&Vec<usize>> = expected.values().collect();
+    assert_eq!(colored.classes().len(), distinct_classes.len());
+    assert_eq!(colored.map().iter().count(), expected.len());
+    assert!(colored
+        .samples(kmer::encode(&[b'A'; K]).unwrap())
+        .is_empty());
+
+    // A read from the core genome and a gene is found in the samples that hold them
+    let mut read = core[100..400].to_vec();
+    read.extend_from_slice(&accessory[1]);
+    let hits = colored.query_sequence(&read);
+    let mut expected_hits = vec![0; 5];
+    for kmer in Kmers::new(&read, K).flatten() {
+        for &i in expected.get(&kmer).into_iter().flatten() {
+            expected_hits[i] += 1;
+        }
+    }
+    assert_eq!(hits, expected_hits);
+    // The k-mers over the junction are in no sample
+    let num_kmers = read.len() as u64 - K as u64 + 1 - (K as u64 - 1);
+    for (i, &hit) in hits.iter().enumerate() {
+        let has_gene = (i + 1) % 3 != 0;
+        assert_eq!(hit == num_kmers, has_gene, "sample {}", i);
+    }
+
+    // Keeping the k-mers seen at least twice leaves the repeated part of the core genome
+    let mut samples = samples;
+    for sample in samples.iter_mut().take(2) {
+        sample
+            .insert_sequence(&core[..500], K)
+            .expect("insert failed!");
+    }
+    let solid = ColoredCqf::build_by(&samples, K, make_sample(LOGN_SLOTS), |_, count, _| {
+        count > 1
+    })
+    .expect("build failed");
+    assert_eq!(solid.classes().len(), 1);
+    let core_kmer = Kmers::new(&core[..K], K).flatten().next().unwrap();
+    assert_eq!(solid.samples(core_kmer), [0, 1]);
+    assert_eq!(
+        solid.map().iter().count() as u64,
+        Kmers::new(&core[..500], K)
+            .flatten()
+            .collect::<HashSet<_>>()
+            .len() as u64
+    );
+
+    assert!(matches!(
+        ColoredCqf::build(&samples, 0, make_sample(LOGN_SLOTS)),
+        Err(CqfError::InvalidArguments)
+    ));
+    let other_width = U32Cqf::new(
+        LOGN_SLOTS,
+        HASH_BITS - 2,
+        true,
+        BuildReversibleHasher::<{ HASH_BITS - 2 }>,
+    )
+    .expect("failed to make cqf");
+    assert!(matches!(
+        ColoredCqf::build(&samples, K, other_width),
+        Err(CqfError::InvalidArguments)
+    ));
+    assert!(matches!(
+        ColoredCqf::build(&samples, K, make_sample(8)),
+        Err(CqfError::Filled)
+    ));
+}
+
+#[test]
+fn many_samples() {
+    let mut rng = Rng::with_seed(8);
+    let shared = random_sequence(&mut rng, 100);
+    let sequences: Vec<Vec<Vec<u8>>> = (0..150)
+        .map(|_| vec![shared.clone(), random_sequence(&mut rng, 40)])
+        .collect();
+    let samples: Vec<Sample> = sequences
+        .iter()
+        .map(|sample| {
+            let mut cqf = make_sample(10);
+            for seq in sample {
+                cqf.insert_sequence(seq, K).expect("insert failed!");
+            }
+            cqf
+        })
+        .collect();
+    let colored = ColoredCqf::build(&samples, K, make_sample(LOGN_SLOTS)).expect("build failed");
+
+    let expected = reference(&sequences);
+    for (&kmer, expected_samples) in expected.iter() {
+        assert_eq!(&colored.samples(kmer), expected_samples);
+    }
+    let id = colored
+        .color_class(Kmers::new(&shared, K).flatten().next().unwrap())
+        .expect("shared k-mer");
+    assert_eq!(colored.classes().samples(id), (0..150).collect::<Vec<_>>());
+    assert!(colored.classes().contains(id, 149));
+    assert!(!colored.classes().contains(id, 150));
+    assert_eq!(colored.classes().get(id).map(|bits| bits.len()), Some(3));
+    assert_eq!(colored.classes().len(), 151);
+}
+
+#[test]
+fn color_class_interning() {
+    let mut classes = ColorClasses::new(130);
+    assert!(classes.is_empty());
+    let mut rng = Rng::with_seed(5);
+    let bitvectors: Vec<Vec<u64>> = (0..2_000)
+        .map(|_| vec![rng.u64(..), rng.u64(..), rng.u64(..4)])
+        .collect();
+    for (id, bitvector) in bitvectors.iter().enumerate() {
+        assert_eq!(classes.intern(bitvector), id as u64);
+    }
+    // Known classes keep their IDs, and each bitvector is stored once
+    for (id, bitvector) in bitvectors.iter().enumerate().rev() {
+        assert_eq!(classes.intern(bitvector), id as u64);
+        assert_eq!(classes.get(id as u64), Some(&bitvector[..]));
+    }
+    assert_eq!(classes.len(), bitvectors.len());
+    assert_eq!(classes.get(bitvectors.len() as u64), None);
+}
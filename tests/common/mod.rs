@@ -0,0 +1,88 @@
+#![allow(dead_code)]
+
+use cqfrs::{CountingQuotientFilter, ReversibleHasher};
+use fastrand::Rng;
+use hashbrown::HashMap;
+
+pub(crate) const fn slots_threshold(logn_slots: u64, fill_factor: f64) -> usize {
+    ((1u64 << logn_slots) as f64 * fill_factor) as usize
+}
+
+pub(crate) fn test_init(num_elements: impl Into<usize>, hash_mask: u64) -> Vec<u64> {
+    let num_elements = num_elements.into();
+    let mut numbers: Vec<u64> = Vec::with_capacity(num_elements);
+    let mut randgen = Rng::new();
+    for _ in 0..num_elements {
+        let num: u64 = randgen.u64(..hash_mask);
+        numbers.push(num & hash_mask);
+    }
+    numbers
+}
+
+pub(crate) fn test_init_map(max_key: usize, max_count: u64) -> HashMap<u64, u64> {
+    let mut numbers: HashMap<u64, u64> = HashMap::with_capacity(max_key);
+    let mut randgen = Rng::new();
+    for k in 0..max_key {
+        if randgen.bool() && randgen.bool() {
+            continue;
+        }
+        let value: u64 = randgen.u64(1..max_count);
+        numbers.insert(k as u64, value);
+    }
+    numbers
+}
+
+/// merge src into dst, summing existing values
+pub(crate) fn map_merge(dst: &mut HashMap<u64, u64>, src: HashMap<u64, u64>) {
+    for (k, v) in src.into_iter() {
+        dst.entry(k).and_modify(|c| *c += v).or_insert(v);
+    }
+}
+
+/// Checks that `cqf` holds exactly the nonzero counts in `expected`, keyed by
+/// item. Items are recovered from hashes, so `cqf` must use a reversible hasher.
+pub(crate) fn check<const HASH_BITS: u64, T: CountingQuotientFilter>(
+    cqf: &T,
+    expected: &HashMap<u64, u64>,
+) {
+    for (&k, &v) in expected.iter() {
+        assert_eq!(cqf.query(k).0, v, "mismatch for key {}", k);
+    }
+    let mut items = 0;
+    for (count, hash) in cqf.iter() {
+        let og = ReversibleHasher::<HASH_BITS>::invert_hash(hash);
+        assert_eq!(expected[&og], count);
+        items += 1;
+    }
+    assert_eq!(items, expected.values().filter(|&&v| v != 0).count());
+    assert_eq!(cqf.stats().occupied_slots, cqf.occupied_slots());
+}
+
+/// Checks that `cqf` holds exactly the nonzero counts in `expected`, keyed by
+/// hash.
+pub(crate) fn check_by_hash<T: CountingQuotientFilter>(cqf: &T, expected: &HashMap<u64, u64>) {
+    for (&hash, &v) in expected.iter() {
+        assert_eq!(cqf.query_by_hash(hash), v, "mismatch for hash {}", hash);
+    }
+    let mut items = 0;
+    for (count, hash) in cqf.iter() {
+        assert_eq!(expected[&hash], count);
+        items += 1;
+    }
+    assert_eq!(items, expected.values().filter(|&&v| v != 0).count());
+    assert_eq!(cqf.stats().occupied_slots, cqf.occupied_slots());
+}
+
+/// Inserts singletons at the last quotient until the run reaches the last slot of `cqf`,
+/// so that growing any of them cannot shift slots further. Returns their hashes.
+pub(crate) fn fill_tail<T: CountingQuotientFilter>(cqf: &mut T) -> Vec<u64> {
+    let last_quotient = (1u64 << cqf.quotient_bits()) - 1;
+    let tail = cqf.stats().num_real_slots - last_quotient;
+    let hashes: Vec<u64> = (0..tail)
+        .map(|r| (last_quotient << cqf.remainder_bits()) | r)
+        .collect();
+    for &hash in hashes.iter() {
+        cqf.insert_by_hash(hash, 1).expect("insert failed!");
+    }
+    hashes
+}
@@ -0,0 +1,96 @@
+use cqfrs::kmer::{self, KmerCoverage};
+use cqfrs::{BuildReversibleHasher, CountingQuotientFilter, CqfError, U32Cqf};
+use fastrand::Rng;
+
+const K: usize = 23;
+const HASH_BITS: u64 = 2 * K as u64;
+const LOGN_SLOTS: u64 = 16;
+
+#[test]
+fn read_coverage() {
+    let mut rng = Rng::with_seed(4);
+    let genome: Vec<u8> = (0..5_000).map(|_| b"ACGT"[rng.usize(..4)]).collect();
+    let mut cqf = U32Cqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    // Reads of 150 bases, with more coverage on the first half of the genome
+    for _ in 0..400 {
+        let start = rng.usize(..genome.len() - 150);
+        cqf.insert_sequence(&genome[start..start + 150], K)
+            .expect("insert failed!");
+    }
+    cqf.insert_sequence(&genome[..2_500], K)
+        .expect("insert failed!");
+
+    // A read with a sequencing error and an N
+    let mut read = genome[1_000..1_200].to_vec();
+    read[50] = if read[50] == b'A' { b'C' } else { b'A' };
+    read[150] = b'N';
+    let coverage = cqf.kmer_coverage(&read, K).expect("coverage failed");
+    assert_eq!(coverage.counts.len(), read.len() - K + 1);
+    let mut expected = Vec::new();
+    for (i, window) in read.windows(K).enumerate() {
+        let count = kmer::encode(window).map(|kmer| cqf.query(kmer::canonical(kmer, K)).0);
+        assert_eq!(coverage.counts[i], count, "position {}", i);
+        expected.extend(count);
+    }
+    // Windows over the error are missing, and windows over the N have no k-mer
+    assert!(coverage.counts[51 - K..=50].iter().all(|&c| c == Some(0)));
+    assert!(coverage.counts[151 - K..=150].iter().all(|&c| c.is_none()));
+    assert_eq!(coverage.num_kmers(), expected.len());
+
+    expected.sort_unstable();
+    assert_eq!(coverage.min(), Some(0));
+    assert_eq!(coverage.median(), Some(expected[(expected.len() - 1) / 2]));
+    let solid = expected.iter().filter(|&&c| c >= 2).count() as f64 / expected.len() as f64;
+    assert_eq!(coverage.solid_fraction(2), solid);
+    assert_eq!(coverage.solid_fraction(0), 1.0);
+
+    // Batched queries match single ones, with repeats and in any order
+    let kmers: Vec<u64> = kmer::Kmers::new(&genome, K)
+        .flatten()
+        .chain(0..1_000)
+        .collect();
+    let batch = cqf.query_batch(kmers.iter().chain(kmers.iter().step_by(3)));
+    for (&kmer, &entry) in kmers
+        .iter()
+        .chain(kmers.iter().step_by(3))
+        .zip(batch.iter())
+    {
+        assert_eq!(entry, cqf.query(kmer));
+    }
+    let hashes: Vec<u64> = batch.iter().map(|&(_, hash)| hash).collect();
+    let counts: Vec<u64> = batch.iter().map(|&(count, _)| count).collect();
+    assert_eq!(cqf.query_batch_by_hash(&hashes), counts);
+}
+
+#[test]
+fn empty_coverage() {
+    let cqf = U32Cqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    let coverage = cqf.kmer_coverage(b"ACGTNACGT", K).expect("coverage failed");
+    assert_eq!(coverage, KmerCoverage::default());
+    assert_eq!(coverage.min(), None);
+    assert_eq!(coverage.median(), None);
+    assert_eq!(coverage.solid_fraction(1), 0.0);
+
+    let coverage = cqf.kmer_coverage(b"ACGTNACGT", 3).expect("coverage failed");
+    assert_eq!(
+        coverage.counts,
+        [Some(0), Some(0), None, None, None, Some(0), Some(0)]
+    );
+    assert_eq!(coverage.median(), Some(0));
+    assert!(matches!(
+        cqf.kmer_coverage(b"ACGT", 40),
+        Err(CqfError::InvalidArguments)
+    ));
+}
@@ -0,0 +1,117 @@
+mod common;
+
+use common::test_init;
+use cqfrs::{
+    BuildReversibleHasher, CountingQuotientFilter, CqfError, CqfMap, ReversibleHasher, U16Cqf,
+    U32Cqf,
+};
+use hashbrown::HashMap;
+
+const LOGN_SLOTS: u64 = 16;
+const HASH_BITS: u64 = 32;
+const VALUE_BITS: u64 = 20;
+
+type Map = CqfMap<U16Cqf<BuildReversibleHasher<HASH_BITS>>>;
+
+fn check(map: &Map, expected: &HashMap<u64, u64>) {
+    for (&k, &v) in expected.iter() {
+        assert_eq!(map.get(k), Some(v), "mismatch for key {}", k);
+    }
+    let mut items = 0;
+    for (hash, value) in map.iter() {
+        let og = ReversibleHasher::<HASH_BITS>::invert_hash(hash);
+        assert_eq!(expected[&og], value);
+        items += 1;
+    }
+    assert_eq!(items, expected.len());
+}
+
+#[test]
+fn map_operations() {
+    let cqf = U16Cqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    let mut map = CqfMap::new(cqf, VALUE_BITS).expect("failed to make map");
+    assert_eq!(map.max_value(), (1 << VALUE_BITS) - 1);
+
+    // Values of 0, which take no counter slot, up to full width
+    let mut expected = HashMap::new();
+    for (k, n) in test_init(20_000usize, (1 << VALUE_BITS) - 1)
+        .into_iter()
+        .enumerate()
+    {
+        let value = n >> (k % VALUE_BITS as usize);
+        let previous = map.insert(k as u64, value).expect("insert failed!");
+        assert_eq!(previous, expected.insert(k as u64, value));
+    }
+    check(&map, &expected);
+
+    // Overwrites keep one entry per key
+    for k in 0..5_000u64 {
+        let previous = map.insert(k, 7).expect("insert failed!");
+        assert_eq!(previous, expected.insert(k, 7));
+    }
+    check(&map, &expected);
+
+    for k in 5_000..10_000u64 {
+        let new = map
+            .update(k, |v| (v * 3) & ((1 << VALUE_BITS) - 1))
+            .expect("update failed");
+        let v = expected.get_mut(&k).expect("key should be present");
+        *v = (*v * 3) & ((1 << VALUE_BITS) - 1);
+        assert_eq!(new, Some(*v));
+    }
+    assert_eq!(
+        map.update(1_000_000u64, |v| v + 1).expect("update failed"),
+        None
+    );
+    check(&map, &expected);
+
+    for k in (0..20_000u64).step_by(3) {
+        assert_eq!(map.remove(k).expect("remove failed!"), expected.remove(&k));
+    }
+    assert_eq!(map.remove(0u64).expect("remove failed!"), None);
+    check(&map, &expected);
+    assert!(!map.contains_key(3u64));
+    assert!(map.contains_key(4u64));
+}
+
+#[test]
+fn map_arguments() {
+    let make = || {
+        U32Cqf::new(
+            LOGN_SLOTS,
+            HASH_BITS,
+            true,
+            BuildReversibleHasher::<HASH_BITS>,
+        )
+        .expect("failed to make cqf")
+    };
+    assert!(matches!(
+        CqfMap::new(make(), 0),
+        Err(CqfError::InvalidArguments)
+    ));
+    assert!(matches!(
+        CqfMap::new(make(), 64),
+        Err(CqfError::InvalidArguments)
+    ));
+    let mut cqf = make();
+    cqf.insert(1u64, 1).expect("insert failed!");
+    assert!(matches!(
+        CqfMap::new(cqf, 8),
+        Err(CqfError::InvalidArguments)
+    ));
+
+    let mut map = CqfMap::new(make(), 8).expect("failed to make map");
+    assert!(matches!(
+        map.insert(1u64, 256),
+        Err(CqfError::InvalidArguments)
+    ));
+    assert_eq!(map.get(1u64), None);
+    assert_eq!(map.insert(1u64, 255).expect("insert failed!"), None);
+    assert_eq!(map.get(1u64), Some(255));
+}
@@ -0,0 +1,107 @@
+mod common;
+
+use common::test_init;
+use cqfrs::{
+    BuildReversibleHasher, CountingQuotientFilter, CqfError, CqfSet, ReversibleHasher, U16Cqf,
+    U32Cqf,
+};
+use hashbrown::HashSet;
+
+const LOGN_SLOTS: u64 = 16;
+const HASH_BITS: u64 = 30;
+
+fn new_set() -> CqfSet<U16Cqf<BuildReversibleHasher<HASH_BITS>>> {
+    let cqf = U16Cqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    CqfSet::new(cqf).expect("failed to make set")
+}
+
+fn check<C: CountingQuotientFilter>(set: &CqfSet<C>, expected: &HashSet<u64>) {
+    for &k in expected.iter() {
+        assert!(set.contains(k), "missing key {}", k);
+    }
+    let items: HashSet<u64> = set
+        .iter()
+        .map(ReversibleHasher::<HASH_BITS>::invert_hash)
+        .collect();
+    assert_eq!(&items, expected);
+    assert_eq!(set.len(), expected.len() as u64);
+    // No entry takes a counter slot
+    assert!(set.cqf().iter().all(|(count, _)| count == 1));
+}
+
+#[test]
+fn set_operations() {
+    let mut set = new_set();
+    let mut expected = HashSet::new();
+    // Many duplicates, which would take counter slots in a counting filter
+    for k in test_init(30_000usize, (1 << 14) - 1) {
+        assert_eq!(set.insert(k).expect("insert failed!"), expected.insert(k));
+    }
+    check(&set, &expected);
+    assert!(!set.contains(1u64 << 14));
+
+    for k in (0..1 << 14).step_by(2) {
+        assert_eq!(set.remove(k).expect("remove failed!"), expected.remove(&k));
+    }
+    check(&set, &expected);
+}
+
+#[test]
+fn union_and_intersection() {
+    let mut set_a = new_set();
+    let mut set_b = new_set();
+    let keys_a: HashSet<u64> = test_init(20_000usize, (1 << 15) - 1).into_iter().collect();
+    let keys_b: HashSet<u64> = test_init(20_000usize, (1 << 15) - 1)
+        .into_iter()
+        .map(|k| k + 20_000)
+        .collect();
+    for &k in keys_a.iter() {
+        set_a.insert(k).expect("insert failed!");
+    }
+    for &k in keys_b.iter() {
+        set_b.insert(k).expect("insert failed!");
+    }
+
+    let new_cqf = || {
+        U32Cqf::new(
+            LOGN_SLOTS,
+            HASH_BITS,
+            true,
+            BuildReversibleHasher::<HASH_BITS>,
+        )
+        .expect("failed to make cqf")
+    };
+    let union = CqfSet::union(&set_a, &set_b, new_cqf()).expect("union failed");
+    check(&union, &keys_a.union(&keys_b).copied().collect());
+    let intersection =
+        CqfSet::intersection(&set_a, &set_b, new_cqf()).expect("intersection failed");
+    check(
+        &intersection,
+        &keys_a.intersection(&keys_b).copied().collect(),
+    );
+
+    // Hash widths must match, and the result must fit
+    let other_width = U32Cqf::new(
+        LOGN_SLOTS,
+        HASH_BITS + 1,
+        true,
+        BuildReversibleHasher::<{ HASH_BITS + 1 }>,
+    )
+    .expect("failed to make cqf");
+    assert!(matches!(
+        CqfSet::union(&set_a, &set_b, other_width),
+        Err(CqfError::InvalidArguments)
+    ));
+    let small = U32Cqf::new(12, HASH_BITS, true, BuildReversibleHasher::<HASH_BITS>)
+        .expect("failed to make cqf");
+    assert!(matches!(
+        CqfSet::union(&set_a, &set_b, small),
+        Err(CqfError::Filled)
+    ));
+}
@@ -0,0 +1,127 @@
+use cqfrs::kmer::{self, Direction};
+use cqfrs::{BuildReversibleHasher, CountingQuotientFilter, CqfError, U32Cqf};
+use fastrand::Rng;
+
+const K: usize = 15;
+const HASH_BITS: u64 = 2 * K as u64;
+const LOGN_SLOTS: u64 = 14;
+
+fn make_cqf() -> U32Cqf<BuildReversibleHasher<HASH_BITS>> {
+    U32Cqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf")
+}
+
+fn random_sequence(rng: &mut Rng, len: usize) -> Vec<u8> {
+    (0..len).map(|_| b"ACGT"[rng.usize(..4)]).collect()
+}
+
+fn walk(cqf: &U32Cqf<BuildReversibleHasher<HASH_BITS>>, seq: &[u8]) -> Vec<u8> {
+    let start = kmer::encode(seq).expect("valid k-mer");
+    let mut unitig = seq.to_vec();
+    for (i, (count, kmer)) in cqf.walk_unitig(start, K).expect("valid k").enumerate() {
+        assert_eq!(count, cqf.query(kmer::canonical(kmer, K)).0);
+        if i > 0 {
+            unitig.push(kmer::decode_base(kmer));
+        }
+    }
+    unitig
+}
+
+#[test]
+fn neighbors_match_queries() {
+    let mut rng = Rng::with_seed(5);
+    let mut cqf = make_cqf();
+    for _ in 0..20 {
+        let seq = random_sequence(&mut rng, 200);
+        cqf.insert_sequence(&seq, K).expect("insert failed!");
+        cqf.insert_sequence(&seq[50..100], K)
+            .expect("insert failed!");
+    }
+    for _ in 0..200 {
+        let seq = random_sequence(&mut rng, K);
+        let kmer = kmer::encode(&seq).expect("valid k-mer");
+        let neighbors = cqf.neighbors(kmer, K).expect("valid k");
+        for (i, &(base, direction, count)) in neighbors.iter().enumerate() {
+            assert_eq!(base, b"ACGT"[i % 4]);
+            let neighbor = if i < 4 {
+                assert_eq!(direction, Direction::Forward);
+                [&seq[1..], &[base]].concat()
+            } else {
+                assert_eq!(direction, Direction::Backward);
+                [&[base], &seq[..K - 1]].concat()
+            };
+            let neighbor = kmer::encode(&neighbor).expect("valid k-mer");
+            assert_eq!(count, cqf.query(kmer::canonical(neighbor, K)).0);
+        }
+    }
+
+    // Both strands of a k-mer see the same neighbors, with directions swapped
+    let seq = random_sequence(&mut rng, 100);
+    cqf.insert_sequence(&seq, K).expect("insert failed!");
+    let kmer = kmer::encode(&seq[40..40 + K]).expect("valid k-mer");
+    let neighbors = cqf.neighbors(kmer, K).expect("valid k");
+    let next = neighbors
+        .iter()
+        .find(|&&(base, direction, _)| direction == Direction::Forward && base == seq[40 + K])
+        .expect("successor");
+    assert!(next.2 > 0);
+    let rc_neighbors = cqf
+        .neighbors(kmer::reverse_complement(kmer, K), K)
+        .expect("valid k");
+    let forward: Vec<u64> = neighbors[..4].iter().map(|n| n.2).collect();
+    let rc_backward: Vec<u64> = rc_neighbors[4..].iter().rev().map(|n| n.2).collect();
+    assert_eq!(forward, rc_backward);
+
+    assert!(matches!(
+        cqf.neighbors(kmer, 0),
+        Err(CqfError::InvalidArguments)
+    ));
+}
+
+#[test]
+fn unitig_walks() {
+    let mut rng = Rng::with_seed(6);
+    let mut cqf = make_cqf();
+    let genome = random_sequence(&mut rng, 1_000);
+    cqf.insert_sequence(&genome, K).expect("insert failed!");
+
+    // A linear sequence is a single unitig, walked from either end
+    assert_eq!(walk(&cqf, &genome[..K]), genome);
+    let rc: Vec<u8> = genome
+        .iter()
+        .rev()
+        .map(|&base| kmer::decode_base(3 - kmer::encode_base(base).expect("base")))
+        .collect();
+    assert_eq!(walk(&cqf, &rc[..K]), rc);
+
+    // A branch after position 600 ends the unitig at the fork, and a second
+    // path into position 800 ends the next one before the join
+    let mut branch = genome[600 - K + 1..600].to_vec();
+    branch.extend(random_sequence(&mut rng, 100));
+    cqf.insert_sequence(&branch, K).expect("insert failed!");
+    let mut join = random_sequence(&mut rng, 100);
+    join.extend_from_slice(&genome[800..800 + K - 1]);
+    cqf.insert_sequence(&join, K).expect("insert failed!");
+    assert_eq!(walk(&cqf, &genome[..K]), genome[..600]);
+    assert_eq!(
+        walk(&cqf, &genome[601 - K..601]),
+        genome[601 - K..800 + K - 1]
+    );
+    assert_eq!(walk(&cqf, &genome[800..800 + K]), genome[800..]);
+
+    // A cycle is walked once
+    let mut cycle = random_sequence(&mut rng, 300);
+    cycle.extend_from_within(..K - 1);
+    let mut cqf = make_cqf();
+    cqf.insert_sequence(&cycle, K).expect("insert failed!");
+    assert_eq!(walk(&cqf, &cycle[..K]), cycle[..300 + K - 1]);
+
+    // A k-mer that is not in the CQF has no unitig
+    let missing = kmer::encode(&random_sequence(&mut rng, K)).expect("valid k-mer");
+    assert_eq!(cqf.walk_unitig(missing, K).expect("valid k").count(), 0);
+}
@@ -0,0 +1,106 @@
+mod common;
+
+use common::{check, check_by_hash, fill_tail, test_init_map};
+use cqfrs::{BuildReversibleHasher, CountingQuotientFilter, CqfError, U16Cqf, U64Cqf};
+use hashbrown::HashMap;
+
+const LOGN_SLOTS: u64 = 16;
+const HASH_BITS: u64 = 30;
+
+#[test]
+fn halving_until_empty() {
+    // Counts up to three 16-bit counter slots
+    let mut elements = test_init_map(15_000, 1 << 40);
+    let mut cqf = U16Cqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    for (&k, &v) in elements.iter() {
+        cqf.insert(k, v).expect("insert failed!");
+    }
+
+    let mut slots = cqf.occupied_slots();
+    while cqf.occupied_slots() != 0 {
+        cqf.decay(0.5).expect("decay failed");
+        elements.values_mut().for_each(|v| *v /= 2);
+        check::<HASH_BITS, _>(&cqf, &elements);
+        assert!(cqf.occupied_slots() <= slots);
+        slots = cqf.occupied_slots();
+    }
+    assert_eq!(cqf.iter().count(), 0);
+}
+
+#[test]
+fn decay_factors() {
+    let elements = test_init_map(20_000, 1000);
+    let mut cqf = U64Cqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    for (&k, &v) in elements.iter() {
+        cqf.insert(k, v).expect("insert failed!");
+    }
+
+    assert!(matches!(cqf.decay(1.5), Err(CqfError::InvalidArguments)));
+    assert!(matches!(cqf.decay(-0.5), Err(CqfError::InvalidArguments)));
+    assert!(matches!(
+        cqf.decay(f64::NAN),
+        Err(CqfError::InvalidArguments)
+    ));
+
+    cqf.decay(1.0).expect("decay failed");
+    check::<HASH_BITS, _>(&cqf, &elements);
+
+    cqf.decay(0.9).expect("decay failed");
+    let mut expected: HashMap<u64, u64> = elements
+        .iter()
+        .map(|(&k, &v)| (k, (v as f64 * 0.9) as u64))
+        .collect();
+    check::<HASH_BITS, _>(&cqf, &expected);
+
+    // The filter keeps working after compaction
+    for k in 100_000..105_000u64 {
+        cqf.insert(k, 3).expect("insert failed!");
+        expected.insert(k, 3);
+    }
+    check::<HASH_BITS, _>(&cqf, &expected);
+
+    cqf.decay(0.0).expect("decay failed");
+    assert_eq!(cqf.occupied_slots(), 0);
+}
+
+#[test]
+fn decay_with_full_tail() {
+    let mut cqf = U16Cqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    let mut expected = HashMap::new();
+    for quotient in (0..60_000u64).step_by(30) {
+        let hash = (quotient << cqf.remainder_bits()) | 3;
+        cqf.insert_by_hash(hash, quotient << 20)
+            .expect("insert failed!");
+        expected.insert(hash, quotient << 20);
+    }
+    let tail = fill_tail(&mut cqf);
+
+    // Nothing can shift past the last slot, which decaying never needs
+    cqf.decay(1.0).expect("decay failed");
+    expected.extend(tail.iter().map(|&hash| (hash, 1)));
+    check_by_hash(&cqf, &expected);
+
+    cqf.decay(0.5).expect("decay failed");
+    expected.values_mut().for_each(|v| *v /= 2);
+    expected.retain(|_, v| *v != 0);
+    check_by_hash(&cqf, &expected);
+    assert_eq!(cqf.stats().distinct_entries, expected.len() as u64);
+}
@@ -0,0 +1,158 @@
+mod common;
+
+use common::{check, check_by_hash, fill_tail, test_init_map};
+use cqfrs::{
+    BuildReversibleHasher, CountingQuotientFilter, CqfError, ReversibleHasher, U32Cqf, U64Cqf,
+};
+use fastrand::Rng;
+use hashbrown::HashMap;
+
+const LOGN_SLOTS: u64 = 16;
+const HASH_BITS: u64 = 40;
+
+#[test]
+fn single_deltas() {
+    let mut elements = test_init_map(20_000, 1 << 36);
+    let mut cqf = U32Cqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    for (&k, &v) in elements.iter() {
+        assert_eq!(cqf.apply_delta(k, v as i64).expect("delta failed"), v);
+    }
+    check::<HASH_BITS, _>(&cqf, &elements);
+
+    // Decreases shrink counters, and reaching zero removes the entry
+    let slots = cqf.occupied_slots();
+    let mut rng = Rng::with_seed(1);
+    for (&k, v) in elements.iter_mut() {
+        let delta = match rng.u8(..3) {
+            0 => *v,
+            1 => *v - 1,
+            _ => rng.u64(..=*v),
+        };
+        assert_eq!(
+            cqf.apply_delta(k, -(delta as i64)).expect("delta failed"),
+            *v - delta
+        );
+        *v -= delta;
+    }
+    elements.retain(|_, v| *v != 0);
+    check::<HASH_BITS, _>(&cqf, &elements);
+    assert!(cqf.occupied_slots() < slots);
+
+    // Going below zero fails without changing the count
+    let (&k, &v) = elements.iter().next().expect("an entry is left");
+    assert!(matches!(
+        cqf.apply_delta(k, -(v as i64) - 1),
+        Err(CqfError::CountUnderflow)
+    ));
+    assert!(matches!(
+        cqf.apply_delta(1_000_000u64, -1),
+        Err(CqfError::CountUnderflow)
+    ));
+    assert_eq!(cqf.apply_delta(k, 0).expect("delta failed"), v);
+    check::<HASH_BITS, _>(&cqf, &elements);
+}
+
+#[test]
+fn batched_deltas() {
+    let mut elements = test_init_map(20_000, 1000);
+    let mut cqf = U64Cqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    cqf.apply_deltas(elements.iter().map(|(&k, &v)| (k, v as i64)))
+        .expect("deltas failed");
+    check::<HASH_BITS, _>(&cqf, &elements);
+
+    // Several deltas per key, summed before they are applied
+    let mut batch = Vec::new();
+    for (&k, v) in elements.iter_mut() {
+        batch.push((k, 5));
+        batch.push((k, -(*v as i64)));
+        if k % 2 == 0 {
+            batch.push((k, -5));
+            *v = 0;
+        } else {
+            *v = 5;
+        }
+    }
+    for k in 100_000..101_000u64 {
+        batch.push((k, 3));
+        elements.insert(k, 3);
+    }
+    cqf.apply_deltas(batch).expect("deltas failed");
+    elements.retain(|_, v| *v != 0);
+    check::<HASH_BITS, _>(&cqf, &elements);
+
+    // A batch that would go below zero is rejected as a whole
+    let batch = vec![(1u64, 10), (100_000u64, -2), (100_001u64, -4)];
+    assert!(matches!(
+        cqf.apply_deltas(batch),
+        Err(CqfError::CountUnderflow)
+    ));
+    check::<HASH_BITS, _>(&cqf, &elements);
+}
+
+#[test]
+fn batched_deltas_filled() {
+    let mut cqf = U32Cqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    let elements: HashMap<u64, u64> = (0..40_000u64).map(|k| (k, 1)).collect();
+    cqf.apply_deltas(elements.iter().map(|(&k, &v)| (k, v as i64)))
+        .expect("deltas failed");
+
+    // Freeing some slots is not enough for the new keys, so nothing is applied
+    let mut batch: Vec<(u64, i64)> = (0..1_000u64).map(|k| (k, -1)).collect();
+    batch.extend((100_000..120_000u64).map(|k| (k, 1)));
+    let occupied = cqf.occupied_slots();
+    assert!(matches!(cqf.apply_deltas(batch), Err(CqfError::Filled)));
+    assert_eq!(cqf.occupied_slots(), occupied);
+    check::<HASH_BITS, _>(&cqf, &elements);
+}
+
+#[test]
+fn batched_deltas_rolled_back() {
+    let mut cqf = U32Cqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    let mut expected = HashMap::new();
+    for quotient in (0..60_000u64).step_by(60) {
+        let hash = (quotient << cqf.remainder_bits()) | 7;
+        cqf.insert_by_hash(hash, 5).expect("insert failed!");
+        expected.insert(hash, 5);
+    }
+    let tail = fill_tail(&mut cqf);
+    expected.extend(tail.iter().map(|&hash| (hash, 1)));
+
+    // Counts grow, entries go away and new ones come in below the tail, then a new key at the
+    // last quotient finds no slot left to shift into
+    let item = |hash| ReversibleHasher::<HASH_BITS>::invert_hash(hash);
+    let mut batch: Vec<(u64, i64)> = Vec::new();
+    for (i, &hash) in expected.keys().filter(|&&hash| hash < tail[0]).enumerate() {
+        batch.push((item(hash), if i % 2 == 0 { 1 << 40 } else { -5 }));
+        batch.push((item(hash + 1), 3));
+    }
+    batch.push((item(tail[tail.len() - 1] + 1), 1));
+
+    let occupied = cqf.occupied_slots();
+    assert!(matches!(cqf.apply_deltas(batch), Err(CqfError::Filled)));
+    assert_eq!(cqf.occupied_slots(), occupied);
+    check_by_hash(&cqf, &expected);
+}
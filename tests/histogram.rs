@@ -0,0 +1,95 @@
+mod common;
+
+use common::test_init_map;
+use cqfrs::{BuildReversibleHasher, CountHistogram, CountingQuotientFilter, U32Cqf, U64Cqf};
+
+#[test]
+fn histogram_matches_map() {
+    const LOGN_SLOTS: u64 = 18;
+    const HASH_BITS: u64 = 40;
+    const MAX_BIN: u64 = 20;
+
+    let elements = test_init_map(30_000, 40);
+    let mut cqf = U32Cqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    for (&k, &v) in elements.iter() {
+        cqf.insert(k, v).expect("insert failed!");
+    }
+
+    let histogram = cqf.count_histogram(MAX_BIN);
+    let mut bins = vec![0u64; MAX_BIN as usize + 1];
+    let mut overflow = 0;
+    for &v in elements.values() {
+        if v <= MAX_BIN {
+            bins[v as usize] += 1;
+        } else {
+            overflow += 1;
+        }
+    }
+    assert_eq!(histogram.bins, bins);
+    assert_eq!(histogram.overflow, overflow);
+    assert_eq!(histogram.distinct, elements.len() as u64);
+    assert_eq!(histogram.total, elements.values().sum::<u64>() as u128);
+    assert_eq!(histogram.singletons, bins[1]);
+    let mean = histogram.total as f64 / histogram.distinct as f64;
+    assert!((histogram.mean() - mean).abs() < 1e-9);
+
+    let total = histogram.total as f64;
+    let entropy: f64 = elements
+        .values()
+        .map(|&v| {
+            let p = v as f64 / total;
+            -p * p.log2()
+        })
+        .sum();
+    assert!((histogram.entropy() - entropy).abs() < 1e-6);
+}
+
+#[test]
+fn parallel_histogram() {
+    const LOGN_SLOTS: u64 = 18;
+    const HASH_BITS: u64 = 40;
+
+    let elements = test_init_map(50_000, 100);
+    let mut cqf = U64Cqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    for (&k, &v) in elements.iter() {
+        cqf.insert(k, v).expect("insert failed!");
+    }
+
+    let serial = cqf.count_histogram(64);
+    for threads in [1, 3, 8] {
+        let parallel = cqf.count_histogram_par(64, threads);
+        assert_eq!(parallel.bins, serial.bins);
+        assert_eq!(parallel.overflow, serial.overflow);
+        assert_eq!(parallel.distinct, serial.distinct);
+        assert_eq!(parallel.total, serial.total);
+    }
+
+    let empty = U64Cqf::new(12, 40, true, BuildReversibleHasher::<40>).expect("failed to make cqf");
+    assert_eq!(empty.count_histogram_par(8, 4).distinct, 0);
+    assert_eq!(empty.count_histogram(8).entropy(), 0.0);
+}
+
+#[test]
+fn histogram_large_counts() {
+    // Spilled counts can reach u64::MAX, and a max_bin that large is clamped
+    let counts = [(u64::MAX, 1), (u64::MAX - 1, 2), (3, 3)];
+    let histogram = CountHistogram::from_counts(counts.into_iter(), u64::MAX);
+    assert_eq!(histogram.max_bin(), CountHistogram::MAX_BIN);
+    assert_eq!(histogram.bins[3], 1);
+    assert_eq!(histogram.overflow, 2);
+    assert_eq!(histogram.distinct, 3);
+    assert_eq!(histogram.total, 2 * u64::MAX as u128 + 2);
+    assert!(histogram.mean() > u64::MAX as f64 / 2.0);
+}
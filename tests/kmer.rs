@@ -0,0 +1,133 @@
+use cqfrs::kmer::{self, Kmers};
+use cqfrs::{BuildReversibleHasher, CountingQuotientFilter, CqfError, ReversibleHasher, U32Cqf};
+use fastrand::Rng;
+use hashbrown::HashMap;
+
+const K: usize = 21;
+const HASH_BITS: u64 = 2 * K as u64;
+const LOGN_SLOTS: u64 = 16;
+
+fn random_sequence(rng: &mut Rng, len: usize) -> Vec<u8> {
+    (0..len).map(|_| b"ACGTacgtN"[rng.usize(..9)]).collect()
+}
+
+fn naive_reverse_complement(seq: &[u8]) -> Vec<u8> {
+    seq.iter()
+        .rev()
+        .map(|&base| match base.to_ascii_uppercase() {
+            b'A' => b'T',
+            b'C' => b'G',
+            b'G' => b'C',
+            b'T' => b'A',
+            other => other,
+        })
+        .collect()
+}
+
+#[test]
+fn encoding() {
+    assert_eq!(kmer::encode(b"ACGT"), Some(0b00_01_10_11));
+    assert_eq!(kmer::encode(b"acgt"), Some(0b00_01_10_11));
+    assert_eq!(kmer::encode(b"ACNT"), None);
+    assert_eq!(kmer::encode(b""), None);
+    assert_eq!(kmer::encode(&[b'A'; 33]), None);
+    assert_eq!(kmer::decode(0b00_01_10_11, 4), b"ACGT");
+    assert_eq!(kmer::reverse_complement(0b00_00_01, 3), 0b10_11_11);
+    assert_eq!(kmer::canonical(0b11_11_11, 3), 0);
+
+    let mut rng = Rng::with_seed(1);
+    for k in 1..=kmer::MAX_K {
+        for _ in 0..100 {
+            let seq: Vec<u8> = (0..k).map(|_| b"ACGT"[rng.usize(..4)]).collect();
+            let encoded = kmer::encode(&seq).expect("valid k-mer");
+            assert_eq!(kmer::decode(encoded, k), seq);
+            let rc = naive_reverse_complement(&seq);
+            assert_eq!(
+                kmer::reverse_complement(encoded, k),
+                kmer::encode(&rc).unwrap()
+            );
+            assert_eq!(
+                kmer::canonical(encoded, k),
+                kmer::encode(seq.min(rc).as_slice()).unwrap()
+            );
+        }
+    }
+}
+
+#[test]
+fn rolling_windows() {
+    let mut rng = Rng::with_seed(2);
+    for k in [1, 5, 21, 31, 32] {
+        for len in [0, k - 1, k, k + 1, 500] {
+            let seq = random_sequence(&mut rng, len);
+            let mut kmers = Kmers::new(&seq, k);
+            assert_eq!(kmers.len(), (len + 1).saturating_sub(k));
+            for window in seq.windows(k) {
+                let expected = kmer::encode(window);
+                assert_eq!(
+                    kmers.next(),
+                    Some(expected.map(|kmer| kmer::canonical(kmer, k)))
+                );
+                assert_eq!(kmers.forward(), expected);
+            }
+            assert_eq!(kmers.next(), None);
+        }
+    }
+}
+
+#[test]
+fn insert_and_decode_sequence() {
+    let mut rng = Rng::with_seed(3);
+    let mut cqf = U32Cqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    let mut expected: HashMap<Vec<u8>, u64> = HashMap::new();
+    let mut inserted = 0;
+    for _ in 0..50 {
+        let mut seq = random_sequence(&mut rng, 300);
+        // Repeats, so some k-mers are counted more than once
+        seq.extend_from_within(..100);
+        for window in seq.windows(K) {
+            if let Some(kmer) = kmer::encode(window) {
+                let canonical = kmer::decode(kmer::canonical(kmer, K), K);
+                *expected.entry(canonical).or_insert(0) += 1;
+            }
+        }
+        inserted += cqf.insert_sequence(&seq, K).expect("insert failed!");
+    }
+    assert_eq!(inserted, expected.values().sum::<u64>());
+
+    let mut items = 0;
+    for (count, hash) in cqf.iter() {
+        let kmer = ReversibleHasher::<HASH_BITS>::invert_hash(hash);
+        assert_eq!(expected[&kmer::decode(kmer, K)], count);
+        items += 1;
+    }
+    assert_eq!(items, expected.len());
+
+    // Either strand queries the same k-mer
+    let (seq, &count) = expected.iter().next().unwrap();
+    let rc = naive_reverse_complement(seq);
+    assert_eq!(cqf.query(kmer::encode(seq).unwrap()).0, count);
+    assert_eq!(
+        cqf.query(kmer::canonical(kmer::encode(&rc).unwrap(), K)).0,
+        count
+    );
+
+    assert_eq!(
+        cqf.insert_sequence(b"NNNNACGT", K).expect("insert failed!"),
+        0
+    );
+    assert!(matches!(
+        cqf.insert_sequence(b"ACGT", 0),
+        Err(CqfError::InvalidArguments)
+    ));
+    assert!(matches!(
+        cqf.insert_sequence(b"ACGT", 33),
+        Err(CqfError::InvalidArguments)
+    ));
+}
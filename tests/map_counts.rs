@@ -0,0 +1,122 @@
+mod common;
+
+use common::{check, check_by_hash, fill_tail, test_init_map};
+use cqfrs::{
+    BuildReversibleHasher, CountingQuotientFilter, CqfError, ReversibleHasher, U32Cqf, U64Cqf,
+};
+use hashbrown::HashMap;
+
+const LOGN_SLOTS: u64 = 16;
+const HASH_BITS: u64 = 36;
+
+#[test]
+fn map_counts_shifts_slots() {
+    // Dense enough for long clusters
+    let mut elements = test_init_map(40_000, 5);
+    let mut cqf = U32Cqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    for (&k, &v) in elements.iter() {
+        cqf.insert(k, v).expect("insert failed!");
+    }
+    check::<HASH_BITS, _>(&cqf, &elements);
+
+    // Halving removes the singletons and turns 2 and 3 into singletons
+    cqf.map_counts(|_, count| count / 2)
+        .expect("map_counts failed");
+    elements.values_mut().for_each(|v| *v /= 2);
+    check::<HASH_BITS, _>(&cqf, &elements);
+
+    // Singletons grow a counter slot, the rest are overwritten in place
+    cqf.map_counts(|_, count| count + 1)
+        .expect("map_counts failed");
+    elements
+        .values_mut()
+        .filter(|v| **v != 0)
+        .for_each(|v| *v += 1);
+    check::<HASH_BITS, _>(&cqf, &elements);
+
+    // The filter keeps working after compaction
+    for k in 100_000..105_000u64 {
+        cqf.insert(k, 1).expect("insert failed!");
+        elements.insert(k, 1);
+    }
+    check::<HASH_BITS, _>(&cqf, &elements);
+}
+
+#[test]
+fn map_counts_by_hash() {
+    let mut elements = test_init_map(30_000, 100);
+    let mut cqf = U64Cqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    for (&k, &v) in elements.iter() {
+        cqf.insert(k, v).expect("insert failed!");
+    }
+
+    // Drop odd keys and cap the rest
+    cqf.map_counts(|hash, count| {
+        if ReversibleHasher::<HASH_BITS>::invert_hash(hash) % 2 == 1 {
+            0
+        } else {
+            count.min(10)
+        }
+    })
+    .expect("map_counts failed");
+    for (&k, v) in elements.iter_mut() {
+        *v = if k % 2 == 1 { 0 } else { (*v).min(10) };
+    }
+    check::<HASH_BITS, _>(&cqf, &elements);
+
+    cqf.map_counts(|_, _| 0).expect("map_counts failed");
+    assert_eq!(cqf.occupied_slots(), 0);
+    assert_eq!(cqf.iter().count(), 0);
+}
+
+#[test]
+fn map_counts_filled_keeps_progress() {
+    let mut cqf = U32Cqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    let mut expected = HashMap::new();
+    for quotient in (0..60_000u64).step_by(60) {
+        let hash = (quotient << cqf.remainder_bits()) | 7;
+        cqf.insert_by_hash(hash, 1).expect("insert failed!");
+        expected.insert(hash, 1);
+    }
+    let tail = fill_tail(&mut cqf);
+    expected.extend(tail.iter().map(|&hash| (hash, 1)));
+
+    // The spread-out entries have room to grow, the first one in the full tail does not
+    let occupied = cqf.occupied_slots();
+    let mut last_hash = 0;
+    assert!(matches!(
+        cqf.map_counts(|hash, count| {
+            last_hash = hash;
+            count + 1
+        }),
+        Err(CqfError::Filled)
+    ));
+    assert_eq!(last_hash, tail[0]);
+    expected
+        .iter_mut()
+        .filter(|(&hash, _)| hash < last_hash)
+        .for_each(|(_, v)| *v += 1);
+    check_by_hash(&cqf, &expected);
+    assert_eq!(
+        cqf.occupied_slots(),
+        occupied + (expected.len() - tail.len()) as u64
+    );
+}
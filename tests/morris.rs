@@ -0,0 +1,103 @@
+use std::collections::hash_map::RandomState;
+
+use cqfrs::{
+    BuildReversibleHasher, CountingQuotientFilter, CqfError, MorrisCqf, U16Cqf, U32Cqf, U8Cqf,
+};
+
+const LOGN_SLOTS: u64 = 16;
+const HASH_BITS: u64 = 32;
+
+fn new_u16(relative_error: f64, seed: u64) -> MorrisCqf<U16Cqf<BuildReversibleHasher<HASH_BITS>>> {
+    let cqf = U16Cqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    MorrisCqf::new(cqf, relative_error, seed).expect("failed to make morris cqf")
+}
+
+#[test]
+fn unbiased_estimates() {
+    const KEYS: u64 = 2_000;
+    const RELATIVE_ERROR: f64 = 0.1;
+
+    for true_count in [1, 10, 1_000, 1_000_000] {
+        let mut cqf = new_u16(RELATIVE_ERROR, true_count);
+        // Insert half the occurrences one at a time and the rest in one batch
+        for k in 0..KEYS {
+            for _ in 0..true_count.min(100) / 2 {
+                cqf.insert(k, 1).expect("insert failed!");
+            }
+            cqf.insert(k, true_count - true_count.min(100) / 2)
+                .expect("insert failed!");
+        }
+
+        let estimates: Vec<f64> = (0..KEYS).map(|k| cqf.query(k)).collect();
+        let mean = estimates.iter().sum::<f64>() / KEYS as f64;
+        let variance = estimates
+            .iter()
+            .map(|e| (e - true_count as f64).powi(2))
+            .sum::<f64>()
+            / KEYS as f64;
+        let relative_error = variance.sqrt() / true_count as f64;
+        assert!(
+            (mean / true_count as f64 - 1.0).abs() < 0.02,
+            "biased mean {} for count {}",
+            mean,
+            true_count
+        );
+        assert!(
+            relative_error < 1.5 * RELATIVE_ERROR,
+            "relative error {} for count {}",
+            relative_error,
+            true_count
+        );
+        // A single counter slot per entry
+        assert!(cqf.cqf().occupied_slots() <= 2 * KEYS);
+        assert_eq!(cqf.iter().count(), KEYS as usize);
+    }
+}
+
+#[test]
+fn seeded_and_saturating() {
+    let run = |seed| {
+        let mut cqf = new_u16(0.05, seed);
+        for k in 0..1_000u64 {
+            cqf.insert(k, k * k).expect("insert failed!");
+        }
+        cqf.iter().collect::<Vec<_>>()
+    };
+    assert_eq!(run(7), run(7));
+    assert_ne!(run(7), run(8));
+
+    let cqf = U8Cqf::new(LOGN_SLOTS, LOGN_SLOTS + 8, false, RandomState::new())
+        .expect("failed to make cqf");
+    let mut cqf = MorrisCqf::new(cqf, 0.1, 1).expect("failed to make morris cqf");
+    assert_eq!(cqf.max_register(), u8::MAX as u64);
+    cqf.insert(1u64, u64::MAX).expect("insert failed!");
+    assert_eq!(cqf.query(1u64), cqf.max_estimate());
+    assert_eq!(cqf.query(2u64), 0.0);
+}
+
+#[test]
+fn morris_arguments() {
+    let make = || {
+        U32Cqf::new(LOGN_SLOTS, HASH_BITS, false, RandomState::new()).expect("failed to make cqf")
+    };
+    assert!(matches!(
+        MorrisCqf::new(make(), 0.0, 0),
+        Err(CqfError::InvalidArguments)
+    ));
+    assert!(matches!(
+        MorrisCqf::new(make(), 1.0, 0),
+        Err(CqfError::InvalidArguments)
+    ));
+    let mut cqf = make();
+    cqf.insert(1u64, 1).expect("insert failed!");
+    assert!(matches!(
+        MorrisCqf::new(cqf, 0.1, 0),
+        Err(CqfError::InvalidArguments)
+    ));
+}
@@ -0,0 +1,124 @@
+mod common;
+
+use common::test_init_map;
+use cqfrs::{
+    BuildReversibleHasher, CountingQuotientFilter, CqfError, CqfMerge, OverflowPolicy, U16Cqf,
+    U64Cqf, U8Cqf,
+};
+
+const LOGN_SLOTS: u64 = 14;
+const HASH_BITS: u64 = 22;
+
+fn new_u8(policy: OverflowPolicy) -> U8Cqf<BuildReversibleHasher<HASH_BITS>> {
+    let mut cqf = U8Cqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    cqf.set_overflow_policy(policy);
+    cqf
+}
+
+#[test]
+fn narrow_counter_policies() {
+    let mut cqf = new_u8(OverflowPolicy::Spill);
+    assert_eq!(cqf.max_count(), u64::MAX);
+    assert_eq!(cqf.insert(1u64, 200).expect("insert failed!"), 200);
+    assert_eq!(cqf.insert(1u64, 100).expect("insert failed!"), 300);
+    assert_eq!(cqf.query(1u64).0, 300);
+
+    let mut cqf = new_u8(OverflowPolicy::Saturate);
+    assert_eq!(cqf.max_count(), u8::MAX as u64);
+    assert_eq!(cqf.insert(1u64, 200).expect("insert failed!"), 200);
+    assert_eq!(cqf.insert(1u64, 100).expect("insert failed!"), 255);
+    assert_eq!(cqf.insert(2u64, 1000).expect("insert failed!"), 255);
+    cqf.set_count(3u64, 256).expect("set_count failed");
+    assert_eq!(cqf.query(3u64).0, 255);
+    cqf.map_counts(|_, count| count * 2)
+        .expect("map_counts failed");
+    assert_eq!(cqf.iter().map(|(count, _)| count).max(), Some(255));
+
+    let mut cqf = new_u8(OverflowPolicy::Error);
+    assert_eq!(cqf.insert(1u64, 200).expect("insert failed!"), 200);
+    assert!(matches!(
+        cqf.insert(1u64, 100),
+        Err(CqfError::CountOverflow)
+    ));
+    assert!(matches!(
+        cqf.insert(2u64, 256),
+        Err(CqfError::CountOverflow)
+    ));
+    assert!(matches!(
+        cqf.set_count(1u64, 1000),
+        Err(CqfError::CountOverflow)
+    ));
+    assert!(matches!(
+        cqf.map_counts(|_, count| count + 100),
+        Err(CqfError::CountOverflow)
+    ));
+    // Failed updates leave the entries untouched
+    assert_eq!(cqf.query(1u64).0, 200);
+    assert_eq!(cqf.query(2u64).0, 0);
+    assert_eq!(cqf.iter().count(), 1);
+}
+
+#[test]
+fn u64_sum_overflow() {
+    let mut cqf = U64Cqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    cqf.insert(1u64, u64::MAX - 1).expect("insert failed!");
+    assert!(matches!(cqf.insert(1u64, 2), Err(CqfError::CountOverflow)));
+    assert_eq!(cqf.query(1u64).0, u64::MAX - 1);
+
+    cqf.set_overflow_policy(OverflowPolicy::Saturate);
+    assert_eq!(cqf.insert(1u64, 2).expect("insert failed!"), u64::MAX);
+}
+
+#[test]
+fn merge_saturates() {
+    let make = || {
+        let mut cqf = U16Cqf::new(
+            LOGN_SLOTS,
+            HASH_BITS,
+            true,
+            BuildReversibleHasher::<HASH_BITS>,
+        )
+        .expect("failed to make cqf");
+        cqf.set_overflow_policy(OverflowPolicy::Saturate);
+        cqf
+    };
+    let elements = test_init_map(5_000, 50_000);
+    let mut cqf1 = make();
+    let mut cqf2 = make();
+    for (&k, &v) in elements.iter() {
+        cqf1.insert(k, v).expect("insert failed!");
+        cqf2.insert(k, v).expect("insert failed!");
+    }
+
+    let mut merged = make();
+    CqfMerge::merge(cqf1.into_iter(), cqf2.into_iter(), &mut merged).expect("merge failed");
+    for (&k, &v) in elements.iter() {
+        assert_eq!(merged.query(k).0, (2 * v).min(u16::MAX as u64));
+    }
+}
+
+#[test]
+fn merge_overflow_errors() {
+    let mut cqf1 = new_u8(OverflowPolicy::Spill);
+    let mut cqf2 = new_u8(OverflowPolicy::Spill);
+    cqf1.insert(1u64, 200).expect("insert failed!");
+    cqf2.insert(1u64, 200).expect("insert failed!");
+
+    let mut merged = new_u8(OverflowPolicy::Error);
+    assert!(matches!(
+        CqfMerge::merge(cqf1.into_iter(), cqf2.into_iter(), &mut merged),
+        Err(CqfError::CountOverflow)
+    ));
+}
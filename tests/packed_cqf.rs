@@ -0,0 +1,163 @@
+mod common;
+
+use std::collections::hash_map::RandomState;
+use std::io::{Seek, SeekFrom, Write};
+
+use common::{check_by_hash, map_merge, test_init, test_init_map};
+use cqfrs::{
+    BuildReversibleHasher, CountingQuotientFilter, CqfError, CqfMerge, OverflowPolicy, PackedCqf,
+    ReversibleHasher,
+};
+use hashbrown::HashMap;
+
+const LOGN_SLOTS: u64 = 16;
+
+#[test]
+fn remainder_widths() {
+    for remainder_bits in [2, 5, 11, 13, 32, 37, 48] {
+        let hash_bits = LOGN_SLOTS + remainder_bits;
+        let mut cqf = PackedCqf::new(LOGN_SLOTS, hash_bits, false, RandomState::new())
+            .expect("failed to make cqf");
+        assert_eq!(cqf.remainder_bits(), remainder_bits);
+
+        let mut expected = HashMap::new();
+        for (i, hash) in test_init(5_000usize, u64::MAX >> (64 - hash_bits))
+            .into_iter()
+            .enumerate()
+        {
+            // Counts from one to several counter slots
+            let count = 1 + (i as u64 % 5) * (i as u64 % 100);
+            let total = expected.entry(hash).or_insert(0);
+            *total += count;
+            assert_eq!(
+                cqf.insert_by_hash(hash, count).expect("insert failed!"),
+                *total
+            );
+        }
+        check_by_hash(&cqf, &expected);
+
+        // Shrink every counter, dropping a third of the entries
+        cqf.map_counts(|_, count| count / 3)
+            .expect("map_counts failed");
+        expected.values_mut().for_each(|v| *v /= 3);
+        expected.retain(|_, v| *v != 0);
+        check_by_hash(&cqf, &expected);
+    }
+}
+
+#[test]
+fn packed_file() {
+    const HASH_BITS: u64 = LOGN_SLOTS + 13;
+
+    let elements = test_init_map(30_000, 300);
+    let mut cqf = PackedCqf::new_file(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+        tempfile::tempfile().expect("failed to make file"),
+    )
+    .expect("failed to make cqf");
+    for (&k, &v) in elements.iter() {
+        cqf.insert(k, v).expect("insert failed!");
+    }
+
+    // 13 bit remainders take 13 words per block instead of 16 for U16
+    let num_blocks = (1 << LOGN_SLOTS) / 64 + 1;
+    assert!(cqf.size_bytes() < num_blocks * (4 + 16) * 8);
+
+    let mut file = tempfile::tempfile().expect("failed to make file");
+    file.write_all(cqf.serialize_to_bytes())
+        .expect("failed to write file");
+    file.seek(SeekFrom::Start(0)).expect("failed to seek file");
+    let cqf =
+        PackedCqf::open_file(BuildReversibleHasher::<HASH_BITS>, file).expect("failed to open cqf");
+    assert_eq!(cqf.remainder_bits(), 13);
+    for (&k, &v) in elements.iter() {
+        assert_eq!(cqf.query(k).0, v, "mismatch for key {}", k);
+    }
+    for (count, hash) in cqf.iter() {
+        let og = ReversibleHasher::<HASH_BITS>::invert_hash(hash);
+        assert_eq!(elements[&og], count);
+    }
+}
+
+#[test]
+fn packed_merge() {
+    const HASH_BITS: u64 = LOGN_SLOTS + 11;
+
+    let mut elements_1 = test_init_map(20_000, 5000);
+    let elements_2 = test_init_map(10_000, 5000);
+    let mut cqf1 = PackedCqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    let mut cqf2 = PackedCqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    for (&k, &v) in elements_1.iter() {
+        cqf1.insert(k, v).expect("insert failed!");
+    }
+    for (&k, &v) in elements_2.iter() {
+        cqf2.insert(k, v).expect("insert failed!");
+    }
+
+    let mut cqf3 = PackedCqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    CqfMerge::merge(cqf1.into_iter(), cqf2.into_iter(), &mut cqf3).expect("merge failed");
+    map_merge(&mut elements_1, elements_2);
+    for (&k, &v) in elements_1.iter() {
+        assert_eq!(cqf3.query(k).0, v, "mismatch for key {}", k);
+    }
+    assert_eq!(cqf3.iter().count(), elements_1.len());
+}
+
+#[test]
+fn packed_arguments() {
+    assert!(matches!(
+        PackedCqf::new(LOGN_SLOTS, LOGN_SLOTS, false, RandomState::new()),
+        Err(CqfError::InvalidArguments)
+    ));
+    assert!(PackedCqf::new(LOGN_SLOTS, 64, false, RandomState::new()).is_ok());
+}
+
+#[test]
+fn narrow_remainder_max_count() {
+    for remainder_bits in [1, 2] {
+        let limit = (1 << remainder_bits) - 1;
+        let mut cqf = PackedCqf::new(
+            LOGN_SLOTS,
+            LOGN_SLOTS + remainder_bits,
+            false,
+            RandomState::new(),
+        )
+        .expect("failed to make cqf");
+        assert_eq!(cqf.max_count(), u64::MAX);
+        assert_eq!(cqf.insert_by_hash(1, 1000).expect("insert failed!"), 1000);
+
+        // One counter slot holds a single remainder-wide digit
+        cqf.set_overflow_policy(OverflowPolicy::Saturate);
+        assert_eq!(cqf.max_count(), limit);
+        assert_eq!(cqf.insert_by_hash(2, 10).expect("insert failed!"), limit);
+
+        cqf.set_overflow_policy(OverflowPolicy::Error);
+        assert_eq!(cqf.max_count(), limit);
+        assert!(matches!(
+            cqf.insert_by_hash(3, limit + 1),
+            Err(CqfError::CountOverflow)
+        ));
+        assert_eq!(cqf.query_by_hash(3), 0);
+    }
+}
@@ -0,0 +1,140 @@
+use cqfrs::kmer::{self, Kmers, SuperKmers};
+use cqfrs::{BuildReversibleHasher, CountingQuotientFilter, CqfError, PartitionedCqf, U32Cqf};
+use fastrand::Rng;
+use hashbrown::HashSet;
+
+const K: usize = 15;
+const M: usize = 7;
+const HASH_BITS: u64 = 2 * K as u64;
+const LOGN_SLOTS: u64 = 14;
+
+type Bucket = U32Cqf<BuildReversibleHasher<HASH_BITS>>;
+
+fn random_sequence(rng: &mut Rng, len: usize) -> Vec<u8> {
+    (0..len)
+        .map(|_| b"ACGTACGTACGTN"[rng.usize(..13)])
+        .collect()
+}
+
+#[test]
+fn super_kmers_cover_kmers() {
+    let mut rng = Rng::with_seed(9);
+    for m in [1, 5, M, K] {
+        let seq = random_sequence(&mut rng, 2_000);
+        let mut kmers = Vec::new();
+        let mut num_super_kmers = 0;
+        for (mmer, super_kmer) in SuperKmers::new(&seq, K, m) {
+            assert!(super_kmer.len() >= K);
+            for kmer in Kmers::new(super_kmer, K) {
+                let kmer = kmer.expect("super-k-mers hold bases only");
+                assert_eq!(kmer::minimizer(kmer, K, m), mmer);
+                kmers.push(kmer);
+            }
+            num_super_kmers += 1;
+        }
+        assert_eq!(kmers, Kmers::new(&seq, K).flatten().collect::<Vec<_>>());
+        if m < K {
+            // Consecutive k-mers mostly share their minimizer
+            assert!(num_super_kmers * 2 < kmers.len(), "m = {}", m);
+        }
+    }
+    assert_eq!(SuperKmers::new(b"ACGTNACGT", 5, 3).count(), 0);
+    let all: Vec<_> = SuperKmers::new(b"ACGTACGT", 8, 8).collect();
+    assert_eq!(all.len(), 1);
+    assert_eq!(all[0].1, b"ACGTACGT");
+}
+
+#[test]
+fn partitioned_counting() {
+    let mut rng = Rng::with_seed(10);
+    let genome = random_sequence(&mut rng, 20_000);
+    let reads: Vec<&[u8]> = (0..300)
+        .map(|_| {
+            let start = rng.usize(..genome.len() - 150);
+            &genome[start..start + 150]
+        })
+        .collect();
+
+    let files = (0..4)
+        .map(|_| tempfile::tempfile().expect("failed to make file"))
+        .collect();
+    let mut partitioned: PartitionedCqf<Bucket> = PartitionedCqf::new_file(
+        files,
+        K,
+        M,
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make buckets");
+    let mut reference = U32Cqf::new(
+        LOGN_SLOTS + 2,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    for read in reads.iter() {
+        let inserted = partitioned.insert_sequence(read).expect("insert failed!");
+        assert_eq!(
+            inserted,
+            reference.insert_sequence(read, K).expect("insert failed!")
+        );
+    }
+    assert!(partitioned.buckets().iter().all(|bucket| bucket.is_file()));
+
+    // Every k-mer is counted in the bucket of its minimizer, and only there
+    let mut seen = HashSet::new();
+    for (i, bucket) in partitioned.buckets().iter().enumerate() {
+        assert!(bucket.occupied_slots() > 0);
+        for (count, hash) in bucket.iter() {
+            let kmer = cqfrs::ReversibleHasher::<HASH_BITS>::invert_hash(hash);
+            assert_eq!(partitioned.bucket_of(kmer), i);
+            assert_eq!(partitioned.query(kmer), count);
+            assert_eq!(reference.query(kmer).0, count);
+            assert!(seen.insert(hash));
+        }
+    }
+    assert_eq!(seen.len(), reference.iter().count());
+    let kmer = reads[0]
+        .windows(K)
+        .find_map(kmer::encode)
+        .expect("valid k-mer");
+    assert_eq!(
+        partitioned.query(kmer::reverse_complement(kmer, K)),
+        reference.query(kmer::canonical(kmer, K)).0
+    );
+
+    let combined = partitioned
+        .combine(
+            U32Cqf::new(
+                LOGN_SLOTS + 2,
+                HASH_BITS,
+                true,
+                BuildReversibleHasher::<HASH_BITS>,
+            )
+            .expect("failed to make cqf"),
+        )
+        .expect("combine failed");
+    assert!(combined.iter().eq(reference.iter()));
+    assert_eq!(combined.occupied_slots(), reference.occupied_slots());
+
+    let small = U32Cqf::new(8, HASH_BITS, true, BuildReversibleHasher::<HASH_BITS>)
+        .expect("failed to make cqf");
+    assert!(matches!(partitioned.combine(small), Err(CqfError::Filled)));
+    assert!(matches!(
+        partitioned.combine(combined),
+        Err(CqfError::InvalidArguments)
+    ));
+
+    let buckets = partitioned.into_buckets();
+    assert!(matches!(
+        PartitionedCqf::from_buckets(Vec::<Bucket>::new(), K, M),
+        Err(CqfError::InvalidArguments)
+    ));
+    assert!(matches!(
+        PartitionedCqf::from_buckets(buckets, K, K + 1),
+        Err(CqfError::InvalidArguments)
+    ));
+}
@@ -0,0 +1,116 @@
+mod common;
+
+use std::collections::hash_map::RandomState;
+
+use common::test_init_map;
+use cqfrs::{
+    CountingQuotientFilter, CqfBackend, CqfError, CqfPlanner, PackedCqf, U16Cqf, U32Cqf, U64Cqf,
+};
+
+#[test]
+fn plan_fits_items() {
+    const NUM_ELEMENTS: usize = 100_000;
+
+    let elements = test_init_map(NUM_ELEMENTS * 4 / 3, 3);
+    let planner = CqfPlanner::new(elements.len() as u64, 0.5, 1e-4);
+
+    let plan = planner.plan(CqfBackend::U32).expect("no plan");
+    assert!(plan.expected_load <= cqfrs::MAX_LOAD_FACTOR);
+    assert!(plan.expected_false_positive_rate <= 1e-4);
+
+    let mut cqf = U32Cqf::new(
+        plan.quotient_bits,
+        plan.hash_bits,
+        false,
+        RandomState::new(),
+    )
+    .expect("failed to make cqf");
+    assert_eq!(cqf.size_bytes(), plan.total_size_bytes);
+    for (&k, &v) in elements.iter() {
+        cqf.insert(k, v).expect("insert failed!");
+    }
+    let false_positives = (0..100_000u64)
+        .map(|i| i + u32::MAX as u64)
+        .filter(|&k| cqf.query(k).0 != 0)
+        .count();
+    assert!(false_positives < 50, "{} false positives", false_positives);
+
+    let plan = planner.plan(CqfBackend::U64).expect("no plan");
+    let cqf = U64Cqf::new(
+        plan.quotient_bits,
+        plan.hash_bits,
+        false,
+        RandomState::new(),
+    )
+    .expect("failed to make cqf");
+    assert_eq!(cqf.size_bytes(), plan.total_size_bytes);
+
+    // The remainders fit in 16 bits but not in 8
+    assert!(planner.plan(CqfBackend::U8).is_err());
+    let plan = planner.plan(CqfBackend::U16).expect("no plan");
+    let cqf = U16Cqf::new(
+        plan.quotient_bits,
+        plan.hash_bits,
+        false,
+        RandomState::new(),
+    )
+    .expect("failed to make cqf");
+    assert_eq!(cqf.size_bytes(), plan.total_size_bytes);
+
+    // Packed remainders are exactly as wide as needed
+    let plan = planner.plan(CqfBackend::Packed).expect("no plan");
+    let cqf = PackedCqf::new(
+        plan.quotient_bits,
+        plan.hash_bits,
+        false,
+        RandomState::new(),
+    )
+    .expect("failed to make cqf");
+    assert_eq!(cqf.size_bytes(), plan.total_size_bytes);
+    assert_eq!(planner.best().map(|p| p.backend), Some(CqfBackend::Packed));
+}
+
+#[test]
+fn plan_limits() {
+    let planner = CqfPlanner::new(1 << 20, 0.0, 1e-12);
+    assert!(matches!(
+        planner.plan(CqfBackend::U32),
+        Err(CqfError::InvalidArguments)
+    ));
+    assert!(planner.plan(CqfBackend::U64).is_ok());
+
+    let planner = CqfPlanner::new(1 << 20, 0.1, 1e-3).with_memory_budget(1 << 20);
+    assert!(matches!(
+        planner.plan(CqfBackend::U64),
+        Err(CqfError::InvalidSize)
+    ));
+    assert!(planner.best().is_none());
+
+    assert!(CqfPlanner::new(10, 0.0, 0.0).plan(CqfBackend::U64).is_err());
+}
+
+#[test]
+fn plan_large_counts() {
+    const NUM_ELEMENTS: u64 = 20_000;
+    const MAX_COUNT: u64 = 1 << 40;
+
+    // Every count takes three 16-bit counter slots, which the default sizing does not cover
+    let planner = CqfPlanner::new(NUM_ELEMENTS, 1.0, 1e-2).with_max_count(MAX_COUNT);
+    let plan = planner.plan(CqfBackend::U16).expect("no plan");
+    let default_plan = CqfPlanner::new(NUM_ELEMENTS, 1.0, 1e-2)
+        .plan(CqfBackend::U16)
+        .expect("no plan");
+    assert!(plan.quotient_bits > default_plan.quotient_bits);
+    assert!(plan.expected_load <= cqfrs::MAX_LOAD_FACTOR);
+
+    let fill = |quotient_bits, hash_bits| {
+        let mut cqf = U16Cqf::new(quotient_bits, hash_bits, false, RandomState::new())
+            .expect("failed to make cqf");
+        (0..NUM_ELEMENTS).try_for_each(|k| cqf.insert(k, MAX_COUNT - k).map(|_| ()))
+    };
+    fill(plan.quotient_bits, plan.hash_bits).expect("insert failed!");
+    assert!(matches!(
+        fill(default_plan.quotient_bits, default_plan.hash_bits),
+        Err(CqfError::Filled)
+    ));
+}
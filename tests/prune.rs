@@ -0,0 +1,81 @@
+mod common;
+
+use common::test_init_map;
+use cqfrs::{BuildReversibleHasher, CountingQuotientFilter, ReversibleHasher, U32Cqf, U64Cqf};
+
+#[test]
+fn prune_by_count() {
+    const LOGN_SLOTS: u64 = 18;
+    const HASH_BITS: u64 = 40;
+
+    let elements = test_init_map(60_000, 10);
+    let mut cqf = U32Cqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    for (&k, &v) in elements.iter() {
+        cqf.insert(k, v).expect("insert failed!");
+    }
+
+    let pruned = cqf.prune(3, 7).expect("prune failed");
+    assert!(pruned.quotient_bits() < cqf.quotient_bits());
+    assert_eq!(pruned.quotient_bits() + pruned.remainder_bits(), HASH_BITS);
+
+    let mut kept = 0;
+    for (&k, &v) in elements.iter() {
+        let expected = if (3..=7).contains(&v) { v } else { 0 };
+        assert_eq!(pruned.query(k).0, expected, "mismatch for key {}", k);
+        kept += (expected != 0) as usize;
+    }
+    assert_eq!(pruned.iter().count(), kept);
+    for (count, hash) in pruned.iter() {
+        let og = ReversibleHasher::<HASH_BITS>::invert_hash(hash);
+        assert_eq!(elements[&og], count);
+    }
+
+    // The pruned filter still takes inserts
+    let mut pruned = pruned;
+    for k in 1_000_000..1_001_000u64 {
+        pruned.insert(k, 2).expect("insert failed!");
+        assert_eq!(pruned.query(k).0, 2);
+    }
+}
+
+#[test]
+fn prune_by_predicate_to_file() {
+    const LOGN_SLOTS: u64 = 18;
+    const HASH_BITS: u64 = 40;
+
+    let elements = test_init_map(60_000, 4);
+    let mut cqf = U64Cqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    for (&k, &v) in elements.iter() {
+        cqf.insert(k, v).expect("insert failed!");
+    }
+
+    let pruned = cqf
+        .prune_by(
+            1,
+            u64::MAX,
+            |hash, _| ReversibleHasher::<HASH_BITS>::invert_hash(hash).is_multiple_of(2),
+            Some(tempfile::tempfile().expect("failed to make file")),
+        )
+        .expect("prune failed");
+    assert!(pruned.is_file());
+    for (&k, &v) in elements.iter() {
+        let expected = if k.is_multiple_of(2) { v } else { 0 };
+        assert_eq!(pruned.query(k).0, expected);
+    }
+
+    let empty = cqf.prune(100, 200).expect("prune failed");
+    assert_eq!(empty.occupied_slots(), 0);
+    assert_eq!(empty.iter().count(), 0);
+}
@@ -0,0 +1,122 @@
+mod common;
+
+use std::collections::BTreeMap;
+use std::ops::Bound;
+
+use common::test_init;
+use cqfrs::{BuildIdentityHasher, CountingQuotientFilter, U32Cqf, U8Cqf};
+use fastrand::Rng;
+
+const LOGN_SLOTS: u64 = 16;
+const HASH_BITS: u64 = 32;
+
+fn expected_range(elements: &BTreeMap<u64, u64>, range: (Bound<u64>, Bound<u64>)) -> (u64, u64) {
+    elements
+        .range(range)
+        .fold((0, 0), |(distinct, total), (_, &v)| {
+            (distinct + 1, total + v)
+        })
+}
+
+#[test]
+fn identity_hash_ranges() {
+    let mut elements = BTreeMap::new();
+    let mut cqf = U32Cqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildIdentityHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    // Clustered keys, with long runs and empty stretches
+    for (i, key) in test_init(20_000usize, (1 << HASH_BITS) - 1)
+        .into_iter()
+        .enumerate()
+    {
+        let key = if i % 8 == 0 { key % (1 << 22) } else { key };
+        let count = 1 + i as u64 % 7;
+        cqf.insert(key, count).expect("insert failed!");
+        *elements.entry(key).or_insert(0) += count;
+    }
+
+    // Hashes are the keys, in key order
+    assert!(cqf
+        .iter()
+        .map(|(count, hash)| (hash, count))
+        .eq(elements.iter().map(|(&k, &v)| (k, v))));
+
+    let mut rng = Rng::with_seed(3);
+    for _ in 0..200 {
+        let a = rng.u64(..1 << HASH_BITS);
+        let b = if rng.bool() {
+            a + rng.u64(..1 << 20)
+        } else {
+            rng.u64(a..=1 << HASH_BITS)
+        };
+        assert_eq!(
+            cqf.range_count(a..b),
+            expected_range(&elements, (Bound::Included(a), Bound::Excluded(b))),
+            "range {}..{}",
+            a,
+            b
+        );
+        assert_eq!(
+            cqf.range_count(a..=b),
+            expected_range(&elements, (Bound::Included(a), Bound::Included(b)))
+        );
+        assert_eq!(
+            cqf.range_count((Bound::Excluded(a), Bound::Unbounded)),
+            expected_range(&elements, (Bound::Excluded(a), Bound::Unbounded))
+        );
+        assert_eq!(
+            cqf.rank(a),
+            expected_range(&elements, (Bound::Unbounded, Bound::Excluded(a)))
+        );
+    }
+
+    let all = expected_range(&elements, (Bound::Unbounded, Bound::Unbounded));
+    assert_eq!(cqf.range_count(..), all);
+    assert_eq!(cqf.rank(1 << HASH_BITS), all);
+    assert_eq!(cqf.range_count(1 << HASH_BITS..), (0, 0));
+    assert_eq!(
+        cqf.range_count((Bound::Excluded(u64::MAX), Bound::Unbounded)),
+        (0, 0)
+    );
+    assert_eq!(cqf.rank(0), (0, 0));
+}
+
+#[test]
+fn rank_multi_slot_counts() {
+    const SMALL_HASH_BITS: u64 = LOGN_SLOTS + 8;
+    let mut elements = BTreeMap::new();
+    let mut cqf = U8Cqf::new(
+        LOGN_SLOTS,
+        SMALL_HASH_BITS,
+        true,
+        BuildIdentityHasher::<SMALL_HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    // Dense keys, so runs spill into later blocks, with counts of up to four counter slots
+    for (i, key) in test_init(15_000usize, (1 << SMALL_HASH_BITS) - 1)
+        .into_iter()
+        .enumerate()
+    {
+        let key = if i % 4 == 0 { key % (1 << 18) } else { key };
+        let count = 1 + (key >> (i as u64 % 4 * 8)) % (1 << 24) * (i as u64 % 2);
+        cqf.insert(key, count).expect("insert failed!");
+        *elements.entry(key).or_insert(0) += count;
+    }
+
+    for (&key, _) in elements.iter().step_by(7) {
+        for hash in [key, key + 1] {
+            assert_eq!(
+                cqf.rank(hash),
+                expected_range(&elements, (Bound::Unbounded, Bound::Excluded(hash))),
+                "rank {}",
+                hash
+            );
+        }
+    }
+    let all = expected_range(&elements, (Bound::Unbounded, Bound::Unbounded));
+    assert_eq!(cqf.rank(1 << SMALL_HASH_BITS), all);
+}
@@ -0,0 +1,121 @@
+mod common;
+
+use common::test_init_map;
+use cqfrs::{BuildReversibleHasher, CountingQuotientFilter, ReversibleHasher, U16Cqf, U32Cqf};
+use fastrand::Rng;
+use hashbrown::HashMap;
+
+const LOGN_SLOTS: u64 = 16;
+const HASH_BITS: u64 = 30;
+
+#[test]
+fn bottom_k_sketch() {
+    let elements = test_init_map(20_000, 10);
+    let mut cqf = U32Cqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    for (&k, &v) in elements.iter() {
+        cqf.insert(k, v).expect("insert failed!");
+    }
+
+    let mut hashes: Vec<u64> = elements.keys().map(|&k| cqf.query(k).1).collect();
+    hashes.sort_unstable();
+    assert_eq!(cqf.sketch(100), hashes[..100]);
+    assert_eq!(cqf.sketch(1_000_000), hashes);
+    assert!(cqf.sketch(0).is_empty());
+}
+
+#[test]
+fn uniform_sample() {
+    // Counts with several counter slots, so runs hold more slots than entries,
+    // and few enough entries to leave blocks empty. The input is seeded so that the
+    // tolerance below is checked against the same draws on every run
+    let mut rng = Rng::with_seed(42);
+    let mut elements: HashMap<u64, u64> = HashMap::new();
+    for k in 0..4_000u64 {
+        if rng.u8(..4) != 0 {
+            elements.insert(k, rng.u64(1..1 << 40));
+        }
+    }
+    let mut cqf = U16Cqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    for (&k, &v) in elements.iter() {
+        cqf.insert(k, v).expect("insert failed!");
+    }
+
+    const N: usize = 100;
+    const DRAWS: u64 = 2_000;
+    let mut picks: HashMap<u64, u64> = HashMap::new();
+    for seed in 0..DRAWS {
+        let sample = cqf.sample(N, seed);
+        assert_eq!(sample.len(), N);
+        assert!(sample.windows(2).all(|w| w[0].1 < w[1].1));
+        for &(count, hash) in sample.iter() {
+            let og = ReversibleHasher::<HASH_BITS>::invert_hash(hash);
+            assert_eq!(elements[&og], count);
+            *picks.entry(hash).or_insert(0) += 1;
+        }
+    }
+    assert_eq!(cqf.sample(N, 7), cqf.sample(N, 7));
+    assert_ne!(cqf.sample(N, 7), cqf.sample(N, 8));
+
+    // Every entry is drawn about DRAWS * N / len times, about 67 here with a standard
+    // deviation of about 8, so the bound is over 4 standard deviations
+    let expected = (DRAWS * N as u64) as f64 / elements.len() as f64;
+    assert_eq!(picks.len(), elements.len());
+    for &picked in picks.values() {
+        assert!(
+            (picked as f64 - expected).abs() < 0.5 * expected,
+            "picked {} times, expected {}",
+            picked,
+            expected
+        );
+    }
+
+    assert_eq!(cqf.sample(1_000_000, 0), cqf.iter().collect::<Vec<_>>());
+}
+
+#[test]
+fn sample_dense() {
+    // Long clusters, with runs pushed past the end of their blocks
+    let elements = test_init_map(20_000, 1_000);
+    let mut cqf = U16Cqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    for (&k, &v) in elements.iter() {
+        cqf.insert(k, v).expect("insert failed!");
+    }
+    // 20 entries for each of 100 quotients, so their runs span dozens of blocks
+    let remainder_bits = HASH_BITS - LOGN_SLOTS;
+    for quotient in 1_000..1_100u64 {
+        for remainder in 0..20u64 {
+            let hash = (quotient << remainder_bits) | (remainder * 97);
+            cqf.insert_by_hash(hash, remainder + 1)
+                .expect("insert failed!");
+        }
+    }
+    assert!(cqf.stats().largest_offset > 64);
+
+    let all: Vec<(u64, u64)> = cqf.iter().collect();
+    for seed in 0..20 {
+        for n in [1, 100, all.len() - 1] {
+            let sample = cqf.sample(n, seed);
+            assert_eq!(sample.len(), n);
+            let mut entries = all.iter();
+            assert!(sample.iter().all(|entry| entries.any(|e| e == entry)));
+        }
+    }
+}
@@ -0,0 +1,195 @@
+mod common;
+
+use common::test_init_map;
+use cqfrs::{BuildReversibleHasher, CountingQuotientFilter, CqfError, U16Cqf, U32Cqf, U64Cqf};
+use hashbrown::{HashMap, HashSet};
+
+const LOGN_SLOTS: u64 = 16;
+const HASH_BITS: u64 = 32;
+
+fn assert_close(actual: f64, expected: f64, metric: &str) {
+    assert!(
+        (actual - expected).abs() < 1e-9,
+        "{}: {} != {}",
+        metric,
+        actual,
+        expected
+    );
+}
+
+#[test]
+fn metrics_match_reference() {
+    let elements_a = test_init_map(20_000, 50);
+    let elements_b: HashMap<u64, u64> = test_init_map(20_000, 50)
+        .into_iter()
+        .map(|(k, v)| (k + 10_000, v))
+        .collect();
+    let mut cqf_a = U32Cqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    // Backends may differ as long as the hashes match
+    let mut cqf_b = U16Cqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    for (&k, &v) in elements_a.iter() {
+        cqf_a.insert(k, v).expect("insert failed!");
+    }
+    for (&k, &v) in elements_b.iter() {
+        cqf_b.insert(k, v).expect("insert failed!");
+    }
+
+    let keys: HashSet<u64> = elements_a
+        .keys()
+        .chain(elements_b.keys())
+        .copied()
+        .collect();
+    let count = |map: &HashMap<u64, u64>, k| map.get(k).copied().unwrap_or(0) as f64;
+    let (mut shared, mut sum_min, mut sum_max, mut dot) = (0.0, 0.0, 0.0, 0.0);
+    for k in keys.iter() {
+        let (a, b) = (count(&elements_a, k), count(&elements_b, k));
+        shared += (a != 0.0 && b != 0.0) as u64 as f64;
+        sum_min += a.min(b);
+        sum_max += a.max(b);
+        dot += a * b;
+    }
+    let norm = |map: &HashMap<u64, u64>| map.values().map(|&v| (v * v) as f64).sum::<f64>().sqrt();
+    let total = |map: &HashMap<u64, u64>| map.values().sum::<u64>() as f64;
+
+    let similarity = cqf_a.similarity(&cqf_b).expect("similarity failed");
+    assert_eq!(similarity.shared, shared as u64);
+    assert_eq!(similarity.union(), keys.len() as u64);
+    assert_close(similarity.jaccard(), shared / keys.len() as f64, "jaccard");
+    assert_close(
+        similarity.weighted_jaccard(),
+        sum_min / sum_max,
+        "weighted jaccard",
+    );
+    assert_close(
+        similarity.containment(),
+        shared / elements_a.len() as f64,
+        "containment",
+    );
+    assert_close(
+        similarity.cosine(),
+        dot / (norm(&elements_a) * norm(&elements_b)),
+        "cosine",
+    );
+    assert_close(
+        similarity.bray_curtis(),
+        1.0 - 2.0 * sum_min / (total(&elements_a) + total(&elements_b)),
+        "bray-curtis",
+    );
+
+    // Symmetric metrics do not depend on the order
+    let reversed = cqf_b.similarity(&cqf_a).expect("similarity failed");
+    assert_close(reversed.jaccard(), similarity.jaccard(), "jaccard");
+    assert_close(reversed.cosine(), similarity.cosine(), "cosine");
+    assert_close(
+        reversed.containment(),
+        shared / elements_b.len() as f64,
+        "containment",
+    );
+}
+
+#[test]
+fn identical_and_empty() {
+    let elements = test_init_map(10_000, 100);
+    let make = || {
+        U64Cqf::new(
+            LOGN_SLOTS,
+            HASH_BITS,
+            true,
+            BuildReversibleHasher::<HASH_BITS>,
+        )
+        .expect("failed to make cqf")
+    };
+    let mut cqf = make();
+    for (&k, &v) in elements.iter() {
+        cqf.insert(k, v).expect("insert failed!");
+    }
+    let empty = make();
+
+    let same = cqf.similarity(&cqf).expect("similarity failed");
+    assert_close(same.jaccard(), 1.0, "jaccard");
+    assert_close(same.weighted_jaccard(), 1.0, "weighted jaccard");
+    assert_close(same.containment(), 1.0, "containment");
+    assert_close(same.cosine(), 1.0, "cosine");
+    assert_close(same.bray_curtis(), 0.0, "bray-curtis");
+
+    let disjoint = cqf.similarity(&empty).expect("similarity failed");
+    assert_close(disjoint.jaccard(), 0.0, "jaccard");
+    assert_close(disjoint.cosine(), 0.0, "cosine");
+    assert_close(disjoint.bray_curtis(), 1.0, "bray-curtis");
+    assert_close(
+        empty
+            .similarity(&empty)
+            .expect("similarity failed")
+            .jaccard(),
+        1.0,
+        "jaccard",
+    );
+
+    let other_width = U64Cqf::new(
+        LOGN_SLOTS,
+        HASH_BITS + 2,
+        true,
+        BuildReversibleHasher::<{ HASH_BITS + 2 }>,
+    )
+    .expect("failed to make cqf");
+    assert!(matches!(
+        cqf.similarity(&other_width),
+        Err(CqfError::InvalidArguments)
+    ));
+}
+
+#[test]
+fn large_counts() {
+    let make = || {
+        U64Cqf::new(
+            LOGN_SLOTS,
+            HASH_BITS,
+            true,
+            BuildReversibleHasher::<HASH_BITS>,
+        )
+        .expect("failed to make cqf")
+    };
+    let (mut cqf_a, mut cqf_b) = (make(), make());
+    for k in 0..100u64 {
+        cqf_a.insert(k, u64::MAX - k).expect("insert failed!");
+        cqf_b
+            .insert(k + 50, u64::MAX - 2 * k)
+            .expect("insert failed!");
+    }
+
+    let similarity = cqf_a.similarity(&cqf_b).expect("similarity failed");
+    let max = u64::MAX as u128;
+    assert_eq!(similarity.total_a, (0..100).map(|k| max - k).sum::<u128>());
+    assert_eq!(
+        similarity.total_b,
+        (0..100).map(|k| max - 2 * k).sum::<u128>()
+    );
+    // Key 50 + k has count MAX - 50 - k in a and MAX - 2k in b
+    let sum_min: u128 = (0..50).map(|k| (max - 50 - k).min(max - 2 * k)).sum();
+    let sum_max: u128 = (0..50)
+        .map(|k| (max - 50 - k).max(max - 2 * k))
+        .sum::<u128>()
+        + (0..50).map(|k| max - k).sum::<u128>()
+        + (50..100).map(|k| max - 2 * k).sum::<u128>();
+    assert_eq!(similarity.sum_min, sum_min);
+    assert_eq!(similarity.sum_max, sum_max);
+    assert_close(
+        similarity.weighted_jaccard(),
+        50.0 / 150.0,
+        "weighted jaccard",
+    );
+    assert_close(similarity.cosine(), 0.5, "cosine");
+    assert_close(similarity.bray_curtis(), 0.5, "bray-curtis");
+}
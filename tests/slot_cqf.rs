@@ -0,0 +1,120 @@
+mod common;
+
+use common::{check, map_merge, test_init, test_init_map};
+use cqfrs::{BuildReversibleHasher, CountingQuotientFilter, CqfMerge, U16Cqf, U32Cqf, U8Cqf};
+use hashbrown::HashMap;
+
+const LOGN_SLOTS: u64 = 16;
+
+/// Tests for a backend storing one remainder per `$slot_bits`-wide slot.
+macro_rules! slot_cqf_tests {
+    ($name:ident, $cqf:ident, $slot_bits:expr) => {
+        mod $name {
+            use super::*;
+
+            const SLOT_BITS: u64 = $slot_bits;
+            const HASH_BITS: u64 = LOGN_SLOTS + SLOT_BITS;
+
+            fn new_cqf() -> $cqf<BuildReversibleHasher<HASH_BITS>> {
+                $cqf::new(
+                    LOGN_SLOTS,
+                    HASH_BITS,
+                    true,
+                    BuildReversibleHasher::<HASH_BITS>,
+                )
+                .expect("failed to make cqf")
+            }
+
+            #[test]
+            fn multi_slot_counts() {
+                let mut cqf = new_cqf();
+
+                // Counts of up to 48 bits, taking several counter slots
+                let mut elements: HashMap<u64, u64> = test_init(10_000usize, u64::MAX)
+                    .into_iter()
+                    .enumerate()
+                    .map(|(k, n)| (k as u64, n >> (16 + n % 48)))
+                    .filter(|&(_, v)| v != 0)
+                    .collect();
+                for (&k, &v) in elements.iter() {
+                    assert_eq!(cqf.insert(k, v).expect("insert failed!"), v);
+                }
+                check::<HASH_BITS, _>(&cqf, &elements);
+
+                // Counters grow a slot when crossing a digit boundary
+                let digit_mask = (1u64 << SLOT_BITS) - 1;
+                for k in 0..2_000u64 {
+                    let v = elements.entry(k).or_insert(0);
+                    let step = (1u64 << SLOT_BITS) - (*v & digit_mask);
+                    *v += step;
+                    assert_eq!(cqf.insert(k, step).expect("insert failed!"), *v);
+                }
+                check::<HASH_BITS, _>(&cqf, &elements);
+
+                // and give it back when shrinking
+                for k in 0..1_000u64 {
+                    cqf.set_count(k, 1).expect("set_count failed!");
+                    elements.insert(k, 1);
+                }
+                cqf.map_counts(|_, count| count >> SLOT_BITS)
+                    .expect("map_counts failed");
+                elements.values_mut().for_each(|v| *v >>= SLOT_BITS);
+                check::<HASH_BITS, _>(&cqf, &elements);
+            }
+
+            #[test]
+            fn incremental_inserts() {
+                let mut cqf = new_cqf();
+                let mut elements = HashMap::new();
+                for round in 0..3u64 {
+                    for k in 0..20_000u64 {
+                        if k % (round + 1) == 0 {
+                            cqf.insert(k, 1).expect("insert failed!");
+                            *elements.entry(k).or_insert(0) += 1;
+                        }
+                    }
+                }
+                check::<HASH_BITS, _>(&cqf, &elements);
+            }
+
+            #[test]
+            fn merge() {
+                let mut elements_1 = test_init_map(10_000, 100_000);
+                let elements_2 = test_init_map(5_000, 100_000);
+
+                let mut cqf1 = new_cqf();
+                let mut cqf2 = new_cqf();
+                for (&k, &v) in elements_1.iter() {
+                    cqf1.insert(k, v).expect("insert failed!");
+                }
+                for (&k, &v) in elements_2.iter() {
+                    cqf2.insert(k, v).expect("insert failed!");
+                }
+
+                let mut cqf3 = new_cqf();
+                CqfMerge::merge(cqf1.iter(), cqf2.iter(), &mut cqf3).expect("merge failed");
+                map_merge(&mut elements_1, elements_2);
+                check::<HASH_BITS, _>(&cqf3, &elements_1);
+
+                // The merged filter still takes inserts
+                for k in 100_000..101_000u64 {
+                    cqf3.insert(k, 300).expect("insert failed!");
+                    elements_1.insert(k, 300);
+                }
+                check::<HASH_BITS, _>(&cqf3, &elements_1);
+            }
+
+            #[test]
+            fn iter_empty() {
+                let cqf = new_cqf();
+                assert_eq!(cqf.iter().count(), 0);
+                assert_eq!(cqf.occupied_slots(), 0);
+                assert_eq!(cqf.into_iter().count(), 0);
+            }
+        }
+    };
+}
+
+slot_cqf_tests!(u8_cqf, U8Cqf, 8);
+slot_cqf_tests!(u16_cqf, U16Cqf, 16);
+slot_cqf_tests!(u32_cqf, U32Cqf, 32);
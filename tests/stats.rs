@@ -0,0 +1,98 @@
+mod common;
+
+use std::collections::HashSet;
+
+use common::test_init_map;
+use cqfrs::{BuildReversibleHasher, CountingQuotientFilter, U32Cqf, U64Cqf};
+
+#[test]
+fn stats_match_contents() {
+    const LOGN_SLOTS: u64 = 18;
+    const HASH_BITS: u64 = 40;
+
+    let elements = test_init_map(120_000, 4);
+    let mut cqf = U64Cqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    for (&k, &v) in elements.iter() {
+        cqf.insert(k, v).expect("insert failed!");
+    }
+
+    let stats = cqf.stats();
+    let entries: Vec<_> = cqf.iter().collect();
+    let quotients: HashSet<_> = entries
+        .iter()
+        .map(|&(_, hash)| hash >> cqf.remainder_bits())
+        .collect();
+
+    assert_eq!(stats.distinct_entries, entries.len() as u64);
+    assert_eq!(
+        stats.counter_slots,
+        entries.iter().filter(|&&(count, _)| count > 1).count() as u64
+    );
+    assert_eq!(stats.occupied_slots, cqf.occupied_slots());
+    assert_eq!(stats.occupied_quotients, quotients.len() as u64);
+    assert_eq!(stats.num_runs(), stats.run_lengths.iter().sum::<u64>());
+    let run_slots: u64 = stats
+        .run_lengths
+        .iter()
+        .enumerate()
+        .map(|(len, &n)| len as u64 * n)
+        .sum();
+    let cluster_slots: u64 = stats
+        .cluster_lengths
+        .iter()
+        .enumerate()
+        .map(|(len, &n)| len as u64 * n)
+        .sum();
+    assert_eq!(run_slots, stats.occupied_slots);
+    assert_eq!(cluster_slots, stats.occupied_slots);
+    assert!(stats.num_clusters() <= stats.num_runs());
+    assert_eq!(
+        stats.region_entries.iter().sum::<u64>(),
+        stats.distinct_entries
+    );
+    assert!(stats.load_factor() > 0.0 && stats.load_factor() < 1.0);
+    assert!(stats.largest_offset as f64 >= stats.average_offset);
+    assert!(stats.false_positive_rate() > 0.0 && stats.false_positive_rate() < 1e-3);
+    // Reversible hashes of a dense key range are spread evenly
+    assert!(stats.region_skew() < 1.5);
+}
+
+#[test]
+fn stats_report_skew() {
+    const LOGN_SLOTS: u64 = 16;
+    const HASH_BITS: u64 = 40;
+
+    let mut cqf = U32Cqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    // All hashes fall in the lowest quotient region
+    for hash in 0..2000u64 {
+        cqf.insert_by_hash(hash << 20, 1).expect("insert failed!");
+    }
+    let stats = cqf.stats();
+    assert_eq!(stats.distinct_entries, 2000);
+    assert_eq!(stats.region_entries[0], 2000);
+    assert_eq!(stats.region_skew(), stats.region_entries.len() as f64);
+
+    let empty = U32Cqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf")
+    .stats();
+    assert_eq!(empty.occupied_slots, 0);
+    assert_eq!(empty.num_clusters(), 0);
+    assert_eq!(empty.largest_offset, 0);
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use common::test_init_map;
+use cqfrs::{
+    BuildReversibleHasher, CountingQuotientFilter, ReversibleHasher, TopK, U32Cqf, U64Cqf,
+};
+
+#[test]
+fn top_k_matches_sorted() {
+    const LOGN_SLOTS: u64 = 18;
+    const HASH_BITS: u64 = 40;
+    const K: usize = 50;
+
+    let elements = test_init_map(20_000, 5_000);
+    let mut cqf = U64Cqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    for (&k, &v) in elements.iter() {
+        cqf.insert(k, v).expect("insert failed!");
+    }
+
+    let mut expected: Vec<_> = cqf.iter().map(|(count, hash)| (hash, count)).collect();
+    expected.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    expected.truncate(K);
+
+    let top = cqf.top_k(K);
+    assert_eq!(top, expected);
+    for (hash, count) in top {
+        let og = ReversibleHasher::<HASH_BITS>::invert_hash(hash);
+        assert_eq!(elements[&og], count);
+    }
+    assert!(cqf.top_k(0).is_empty());
+}
+
+#[test]
+fn streaming_top_k() {
+    const LOGN_SLOTS: u64 = 16;
+    const HASH_BITS: u64 = 40;
+    const K: usize = 10;
+
+    let mut cqf = U32Cqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    let mut tracker = TopK::new(K);
+
+    // Key i is inserted i + 1 times, interleaved so the ranking changes while inserting
+    for round in 0..100u64 {
+        for key in round..100 {
+            let count = cqf
+                .insert_tracked(key, 1, &mut tracker)
+                .expect("insert failed!");
+            assert_eq!(count, round + 1);
+        }
+    }
+
+    assert_eq!(tracker.len(), K);
+    assert_eq!(tracker.to_vec(), cqf.top_k(K));
+    assert_eq!(tracker.min_count(), Some(91));
+}
+
+#[test]
+fn evicted_hash_reenters() {
+    let mut tracker = TopK::new(2);
+    tracker.observe(1, 5);
+    tracker.observe(2, 6);
+    tracker.observe(3, 7);
+    assert_eq!(tracker.to_vec(), [(3, 7), (2, 6)]);
+
+    // Hash 1 was evicted, and comes back once its count beats the smallest tracked one
+    tracker.observe(1, 6);
+    assert_eq!(tracker.to_vec(), [(3, 7), (2, 6)]);
+    tracker.observe(1, 8);
+    assert_eq!(tracker.to_vec(), [(1, 8), (3, 7)]);
+}
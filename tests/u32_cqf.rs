@@ -191,7 +191,7 @@ fn simple_merge() {
 
     eprintln!("Starting merge");
     let now = std::time::Instant::now();
-    CqfMerge::merge(cqf1.into_iter(), cqf2.into_iter(), &mut cqf3);
+    CqfMerge::merge(cqf1.into_iter(), cqf2.into_iter(), &mut cqf3).expect("merge failed");
     let elapsed = now.elapsed();
     eprintln!(
         "Merge took {:?} ({:?} per iter)",
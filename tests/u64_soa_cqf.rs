@@ -0,0 +1,121 @@
+mod common;
+
+use common::{map_merge, slots_threshold, test_init, test_init_map};
+use cqfrs::{
+    BuildReversibleHasher, CountingQuotientFilter, CqfMerge, ReversibleHasher, U64Cqf, U64SoaCqf,
+};
+use hashbrown::HashMap;
+
+#[test]
+fn consuming_iter() {
+    const LOGN_SLOTS: u64 = 20;
+    let elements = test_init(slots_threshold(LOGN_SLOTS, 0.75), (1 << 46) - 1);
+    let mut cqf = U64SoaCqf::new(LOGN_SLOTS, 46, true, BuildReversibleHasher::<46>)
+        .expect("failed to make cqf");
+
+    let mut temp: HashMap<u64, u64> = HashMap::new();
+    for el in elements.iter().copied() {
+        cqf.insert(el, 1).expect("insert failed!");
+        *temp.entry(el).or_insert(0) += 1;
+    }
+
+    for (&k, &v) in temp.iter() {
+        let count = cqf.query(k);
+        assert_eq!(count.0, v);
+    }
+
+    for (c, h) in cqf.into_iter() {
+        let og = ReversibleHasher::<46>::invert_hash(h);
+        let count = temp.get(&og).unwrap();
+        assert_eq!(count, &c);
+    }
+}
+
+#[test]
+fn ref_iter() {
+    const LOGN_SLOTS: u64 = 20;
+    let elements = test_init(slots_threshold(LOGN_SLOTS, 0.75), (1 << 46) - 1);
+    let mut cqf = U64SoaCqf::new(LOGN_SLOTS, 46, true, BuildReversibleHasher::<46>)
+        .expect("failed to make cqf");
+
+    let mut temp: HashMap<u64, u64> = HashMap::new();
+    for el in elements.iter().copied() {
+        cqf.insert(el, 1).expect("insert failed!");
+        *temp.entry(el).or_insert(0) += 1;
+    }
+
+    for (&k, &v) in temp.iter() {
+        let count = cqf.query(k);
+        assert_eq!(count.0, v);
+    }
+
+    for (c, h) in cqf.iter() {
+        let og = ReversibleHasher::<46>::invert_hash(h);
+        let count = temp.get(&og).unwrap();
+        assert_eq!(count, &c);
+    }
+}
+
+#[test]
+fn matches_aos_layout() {
+    const LOGN_SLOTS: u64 = 18;
+    const HASH_BITS: u64 = 40;
+
+    let mut elements_1 = test_init_map(slots_threshold(LOGN_SLOTS, 0.3), 100);
+    let elements_2 = test_init_map(slots_threshold(LOGN_SLOTS, 0.2), 100);
+    let mut soa1 = U64SoaCqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    let mut soa2 = U64SoaCqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    let mut aos = U64Cqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    for (&k, &v) in elements_1.iter() {
+        soa1.insert(k, v).expect("insert failed!");
+        aos.insert(k, v).expect("insert failed!");
+    }
+    for (&k, &v) in elements_2.iter() {
+        soa2.insert(k, v).expect("insert failed!");
+        aos.insert(k, v).expect("insert failed!");
+    }
+
+    let mut merged = U64SoaCqf::new(
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make cqf");
+    CqfMerge::merge(soa1.iter(), soa2.iter(), &mut merged).expect("merge failed");
+    map_merge(&mut elements_1, elements_2);
+
+    // Same entries in the same slots as the array-of-structs layout
+    assert!(merged.iter().eq(aos.iter()));
+    assert_eq!(merged.occupied_slots(), aos.occupied_slots());
+    assert_eq!(merged.stats().run_lengths, aos.stats().run_lengths);
+    assert_eq!(merged.stats().cluster_lengths, aos.stats().cluster_lengths);
+    for (&k, &v) in elements_1.iter() {
+        assert_eq!(merged.query(k).0, v, "mismatch for key {}", k);
+    }
+
+    merged
+        .map_counts(|_, count| count / 2)
+        .expect("map_counts failed");
+    aos.map_counts(|_, count| count / 2)
+        .expect("map_counts failed");
+    assert!(merged.iter().eq(aos.iter()));
+}
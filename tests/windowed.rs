@@ -0,0 +1,103 @@
+mod common;
+
+use std::collections::VecDeque;
+
+use common::{map_merge, test_init_map};
+use cqfrs::{
+    BuildReversibleHasher, CountingQuotientFilter, CqfError, ReversibleHasher, U32Cqf, U64Cqf,
+    WindowedCqf,
+};
+use hashbrown::HashMap;
+
+const LOGN_SLOTS: u64 = 14;
+const HASH_BITS: u64 = 34;
+
+fn window_counts(intervals: &VecDeque<HashMap<u64, u64>>) -> HashMap<u64, u64> {
+    let mut expected = HashMap::new();
+    for interval in intervals.iter() {
+        map_merge(&mut expected, interval.clone());
+    }
+    expected
+}
+
+#[test]
+fn rotating_window() {
+    const GENERATIONS: usize = 3;
+
+    let mut window = WindowedCqf::<U32Cqf<_>>::new(
+        GENERATIONS,
+        LOGN_SLOTS,
+        HASH_BITS,
+        true,
+        BuildReversibleHasher::<HASH_BITS>,
+    )
+    .expect("failed to make window");
+    let mut intervals = VecDeque::new();
+    for interval in 0..6 {
+        if interval > 0 {
+            let expired = window.rotate().expect("rotate failed");
+            assert_eq!(expired.is_some(), intervals.len() == GENERATIONS);
+            if let Some(expired) = expired {
+                let oldest: HashMap<u64, u64> = intervals.pop_front().expect("oldest interval");
+                assert_eq!(expired.iter().count(), oldest.len());
+            }
+        }
+        // Keys shift between intervals so some expire with their generation
+        let counts: HashMap<u64, u64> = test_init_map(5_000, 20)
+            .into_iter()
+            .map(|(k, v)| (k + interval * 1_000, v))
+            .collect();
+        for (&k, &v) in counts.iter() {
+            window.insert(k, v).expect("insert failed!");
+        }
+        intervals.push_back(counts);
+        assert_eq!(window.generations().count(), intervals.len());
+
+        let expected = window_counts(&intervals);
+        for k in 0..12_000u64 {
+            assert_eq!(
+                window.query(k).0,
+                expected.get(&k).copied().unwrap_or(0),
+                "mismatch for key {} in interval {}",
+                k,
+                interval
+            );
+        }
+    }
+}
+
+#[test]
+fn merged_window() {
+    let mut window =
+        WindowedCqf::<U64Cqf<_>>::new(4, 12, HASH_BITS, true, BuildReversibleHasher::<HASH_BITS>)
+            .expect("failed to make window");
+    let mut intervals = VecDeque::new();
+    for interval in 0..4 {
+        if interval > 0 {
+            window.rotate().expect("rotate failed");
+        }
+        let counts: HashMap<u64, u64> = test_init_map(2_000, 2)
+            .into_iter()
+            .map(|(k, v)| (k + interval * 1_500, v))
+            .collect();
+        for (&k, &v) in counts.iter() {
+            window.insert(k, v).expect("insert failed!");
+        }
+        intervals.push_back(counts);
+    }
+
+    // The window holds more entries than a single generation
+    let merged = window.merged().expect("merge failed");
+    assert!(merged.quotient_bits() > window.current().quotient_bits());
+    let expected = window_counts(&intervals);
+    assert_eq!(merged.iter().count(), expected.len());
+    for (count, hash) in merged.iter() {
+        let og = ReversibleHasher::<HASH_BITS>::invert_hash(hash);
+        assert_eq!(expected[&og], count);
+    }
+
+    assert!(matches!(
+        WindowedCqf::<U64Cqf<_>>::new(0, 12, HASH_BITS, true, BuildReversibleHasher::<HASH_BITS>),
+        Err(CqfError::InvalidArguments)
+    ));
+}